@@ -0,0 +1,107 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use clap::Args;
+
+use crate::config::Config;
+
+#[derive(Args, Debug)]
+pub struct CleanArgs {
+    /// List what would be removed without removing anything
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Only remove the fetched feed data, keep the built site
+    #[arg(long)]
+    pub data_only: bool,
+}
+
+pub fn run(config: Config, args: CleanArgs) -> Result<()> {
+    let artifacts: Vec<_> = generated_artifacts(&config, args.data_only)
+        .into_iter()
+        .filter(|path| path.exists())
+        .collect();
+    if artifacts.is_empty() {
+        log::info!("Nothing to clean");
+        return Ok(());
+    }
+
+    for path in artifacts {
+        if args.dry_run {
+            println!("Would remove {}", path.display());
+            continue;
+        }
+        let result = if path.is_dir() {
+            std::fs::remove_dir_all(&path)
+        } else {
+            std::fs::remove_file(&path)
+        };
+        result.with_context(|| format!("Failed to remove {}", path.display()))?;
+        log::info!("Removed {}", path.display());
+    }
+    Ok(())
+}
+
+/// Everything spacefeeder or the site build writes, whether or not it exists
+fn generated_artifacts(config: &Config, data_only: bool) -> Vec<PathBuf> {
+    let output_config = &config.output_config;
+    let mut artifacts = vec![
+        PathBuf::from(&output_config.feed_data_output_path),
+        PathBuf::from(&output_config.item_data_output_path),
+        PathBuf::from(&output_config.build_info_output_path),
+    ];
+    artifacts.extend(config.tiers.iter().map(|tier| {
+        Path::new(&output_config.tier_data_output_dir).join(format!("{tier}Data.json"))
+    }));
+    if !data_only {
+        artifacts.push(PathBuf::from(&output_config.site_output_dir));
+    }
+    artifacts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(false, false, &["spacefeeder.toml", "static/css/styles.css", "templates/index.html"]; "clean everything")]
+    #[test_case(false, true, &["public/index.html", "spacefeeder.toml", "static/css/styles.css", "templates/index.html"]; "data only")]
+    #[test_case(true, false, ALL_FILES; "dry run")]
+    fn test_clean(dry_run: bool, data_only: bool, expected: &[&str]) {
+        let dir = tempfile::tempdir().unwrap();
+        for file in ALL_FILES {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+
+        let mut config = Config::default();
+        let path = |p: &str| dir.path().join(p).to_string_lossy().into_owned();
+        config.output_config.feed_data_output_path = path("content/data/feedData.json");
+        config.output_config.item_data_output_path = path("content/data/itemData.json");
+        config.output_config.tier_data_output_dir = path("content/data");
+        config.output_config.build_info_output_path = path("static/build-info.json");
+        config.output_config.site_output_dir = path("public");
+        let args = CleanArgs { dry_run, data_only };
+        run(config, args).unwrap();
+
+        let remaining: Vec<_> = ALL_FILES
+            .iter()
+            .copied()
+            .filter(|file| dir.path().join(file).exists())
+            .collect();
+        assert_eq!(remaining, expected);
+    }
+
+    const ALL_FILES: &[&str] = &[
+        "content/data/feedData.json",
+        "content/data/itemData.json",
+        "content/data/likeData.json",
+        "content/data/loveData.json",
+        "content/data/newData.json",
+        "public/index.html",
+        "spacefeeder.toml",
+        "static/build-info.json",
+        "static/css/styles.css",
+        "templates/index.html",
+    ];
+}
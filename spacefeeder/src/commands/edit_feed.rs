@@ -0,0 +1,338 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use serde_json::Value;
+use toml_edit::{value, DocumentMut, Item, TableLike};
+
+use crate::config::Config;
+use crate::{is_file_name_safe, Tier};
+
+#[derive(Args, Debug)]
+pub struct FeedArgs {
+    /// Slug of the feed, as in [feeds.<slug>]
+    pub slug: String,
+}
+
+#[derive(Args, Debug)]
+pub struct RenameArgs {
+    /// Current slug of the feed
+    pub old: String,
+    /// New slug, made of lowercase letters, digits, '_' and '-'
+    pub new: String,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum TierStep {
+    /// Towards the most loved tier
+    Promote,
+    /// Towards the least loved tier
+    Demote,
+}
+
+/// Enable or disable a feed in the config file
+pub fn set_enabled(config_path: &Path, args: FeedArgs, enabled: bool) -> Result<()> {
+    edit_feed(config_path, &args.slug, |feed| {
+        // Feeds are enabled unless stated otherwise
+        if enabled {
+            feed.remove("enabled");
+        } else {
+            feed.insert("enabled", value(false));
+        }
+    })?;
+    let state = if enabled { "Enabled" } else { "Disabled" };
+    log::info!("{state} feed {}", args.slug);
+    Ok(())
+}
+
+/// Move a feed one tier up or down in the config file
+pub fn move_tier(config_path: &Path, args: FeedArgs, step: TierStep) -> Result<()> {
+    let config = Config::from_file(config_path)?;
+    let current = &config
+        .feeds
+        .get(&args.slug)
+        .with_context(|| format!("No feed named {}", args.slug))?
+        .tier;
+    let Some(next) = next_tier(&config.tiers, current, step) else {
+        log::info!("{} is already in the {current} tier", args.slug);
+        return Ok(());
+    };
+    edit_feed(config_path, &args.slug, |feed| {
+        feed.insert("tier", value(next.as_str()));
+    })?;
+    log::info!("Moved {} from {current} to {next}", args.slug);
+    Ok(())
+}
+
+/// Give a feed a new slug in the config file and in the fetched data, so
+/// that it keeps its items even while disabled
+pub fn rename(config_path: &Path, args: RenameArgs) -> Result<()> {
+    let RenameArgs { old, new } = args;
+    if !is_file_name_safe(&new) {
+        bail!("Invalid slug {new:?}, only lowercase letters, digits, '_' and '-' are allowed");
+    }
+    let config = Config::from_file(config_path)?;
+    if !config.feeds.contains_key(&old) {
+        bail!("No feed named {old}");
+    }
+    if config.feeds.contains_key(&new) {
+        bail!("There already is a feed named {new}");
+    }
+
+    // Prepare every change before writing any, so that a broken file doesn't
+    // leave the rename half done
+    let content = std::fs::read_to_string(config_path)
+        .with_context(|| format!("Failed to read file: {}", config_path.display()))?;
+    let content = rename_feed_toml(&content, &old, &new)
+        .with_context(|| format!("Invalid config in file: {}", config_path.display()))?;
+    let mut data_files = Vec::new();
+    for path in data_files_with_slugs(&config) {
+        if !path.exists() {
+            continue;
+        }
+        let data = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read file: {}", path.display()))?;
+        let (data, renamed) = rename_in_data(&data, &old, &new)
+            .with_context(|| format!("Invalid data in file: {}", path.display()))?;
+        if renamed > 0 {
+            data_files.push((path, data, renamed));
+        }
+    }
+
+    std::fs::write(config_path, content)
+        .with_context(|| format!("Failed to write file: {}", config_path.display()))?;
+    log::info!("Renamed feed {old} to {new} in {}", config_path.display());
+    for (path, data, renamed) in data_files {
+        std::fs::write(&path, data)
+            .with_context(|| format!("Failed to write file: {}", path.display()))?;
+        log::info!("Renamed {renamed} entries in {}", path.display());
+    }
+    Ok(())
+}
+
+/// The fetched data files whose entries carry the slug of their feed
+fn data_files_with_slugs(config: &Config) -> Vec<PathBuf> {
+    let output_config = &config.output_config;
+    let mut paths = vec![
+        PathBuf::from(&output_config.feed_data_output_path),
+        PathBuf::from(&output_config.item_data_output_path),
+    ];
+    paths.extend(config.tiers.iter().map(|tier| {
+        Path::new(&output_config.tier_data_output_dir).join(format!("{tier}Data.json"))
+    }));
+    paths
+}
+
+/// Change the slug of every entry of the feed in a JSON data file, returning
+/// the new contents and how many entries changed
+fn rename_in_data(content: &str, old: &str, new: &str) -> Result<(String, usize)> {
+    let mut data: Value = serde_json::from_str(content).context("Failed to parse JSON")?;
+    let entries = data.as_array_mut().context("Expected a list of entries")?;
+    let mut renamed = 0;
+    for entry in entries {
+        if entry.get("slug").and_then(Value::as_str) == Some(old) {
+            entry["slug"] = Value::from(new);
+            renamed += 1;
+        }
+    }
+    Ok((serde_json::to_string_pretty(&data)?, renamed))
+}
+
+fn rename_feed_toml(content: &str, old: &str, new: &str) -> Result<String> {
+    let mut doc: DocumentMut = content.parse().context("Failed to parse TOML")?;
+    let feeds = doc
+        .get_mut("feeds")
+        .and_then(Item::as_table_like_mut)
+        .context("No feeds in config")?;
+    let feed = feeds
+        .remove(old)
+        .with_context(|| format!("No feed named {old}"))?;
+    feeds.insert(new, feed);
+    Ok(doc.to_string())
+}
+
+/// The tier after `current` in `tiers`, if there is one in that direction
+fn next_tier<'a>(tiers: &'a [Tier], current: &Tier, step: TierStep) -> Option<&'a Tier> {
+    let index = tiers.iter().position(|tier| tier == current)?;
+    match step {
+        TierStep::Promote => tiers.get(index + 1),
+        TierStep::Demote => index.checked_sub(1).and_then(|index| tiers.get(index)),
+    }
+}
+
+/// Change the table of one feed in the config file, keeping the rest of the
+/// file, including comments, as it is
+fn edit_feed<F>(config_path: &Path, slug: &str, edit: F) -> Result<()>
+where
+    F: FnOnce(&mut dyn TableLike),
+{
+    let content = std::fs::read_to_string(config_path)
+        .with_context(|| format!("Failed to read file: {}", config_path.display()))?;
+    let content = edit_feed_toml(&content, slug, edit)
+        .with_context(|| format!("Invalid config in file: {}", config_path.display()))?;
+    std::fs::write(config_path, content)
+        .with_context(|| format!("Failed to write file: {}", config_path.display()))
+}
+
+fn edit_feed_toml<F>(content: &str, slug: &str, edit: F) -> Result<String>
+where
+    F: FnOnce(&mut dyn TableLike),
+{
+    let mut doc: DocumentMut = content.parse().context("Failed to parse TOML")?;
+    let feed = doc
+        .get_mut("feeds")
+        .and_then(Item::as_table_like_mut)
+        .and_then(|feeds| feeds.get_mut(slug))
+        .and_then(Item::as_table_like_mut)
+        .with_context(|| format!("No feed named {slug}"))?;
+    edit(feed);
+    Ok(doc.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    const CONFIG: &str = r#"max_articles = 5
+description_max_words = 150
+
+# A noisy one
+[feeds.example]
+url = "https://example.com/feed"
+author = "Example"
+tier = "new"
+"#;
+
+    fn args(slug: &str) -> FeedArgs {
+        FeedArgs {
+            slug: slug.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_set_enabled() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("spacefeeder.toml");
+        std::fs::write(&config_path, CONFIG).unwrap();
+
+        set_enabled(&config_path, args("example"), false).unwrap();
+        let config = Config::from_file(&config_path).unwrap();
+        assert!(!config.feeds["example"].is_enabled());
+        let content = std::fs::read_to_string(&config_path).unwrap();
+        assert!(content.contains("# A noisy one"));
+
+        set_enabled(&config_path, args("example"), true).unwrap();
+        let content = std::fs::read_to_string(&config_path).unwrap();
+        assert_eq!(content, CONFIG);
+
+        assert!(set_enabled(&config_path, args("missing"), false).is_err());
+    }
+
+    #[test_case("new", TierStep::Promote, Some("like"); "promote")]
+    #[test_case("love", TierStep::Promote, None; "promote at the top")]
+    #[test_case("like", TierStep::Demote, Some("new"); "demote")]
+    #[test_case("new", TierStep::Demote, None; "demote at the bottom")]
+    fn test_next_tier(current: &str, step: TierStep, expected: Option<&str>) {
+        let tiers = Config::default().tiers;
+        let next = next_tier(&tiers, &current.parse().unwrap(), step);
+        assert_eq!(next.map(Tier::as_str), expected);
+    }
+
+    #[test]
+    fn test_move_tier() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("spacefeeder.toml");
+        std::fs::write(&config_path, CONFIG).unwrap();
+
+        move_tier(&config_path, args("example"), TierStep::Promote).unwrap();
+        move_tier(&config_path, args("example"), TierStep::Promote).unwrap();
+        move_tier(&config_path, args("example"), TierStep::Promote).unwrap();
+        let config = Config::from_file(&config_path).unwrap();
+        assert_eq!(config.feeds["example"].tier.as_str(), "love");
+        let content = std::fs::read_to_string(&config_path).unwrap();
+        assert!(content.contains("# A noisy one"));
+    }
+
+    /// CONFIG with its data files placed in `dir`
+    fn write_config(dir: &Path) -> PathBuf {
+        let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+        let content = format!(
+            "feed_data_output_path = {:?}\nitem_data_output_path = {:?}\ntier_data_output_dir = {:?}\n{CONFIG}",
+            path("feedData.json"),
+            path("itemData.json"),
+            path(""),
+        );
+        let config_path = dir.join("spacefeeder.toml");
+        std::fs::write(&config_path, content).unwrap();
+        config_path
+    }
+
+    fn rename_args(old: &str, new: &str) -> RenameArgs {
+        RenameArgs {
+            old: old.to_string(),
+            new: new.to_string(),
+        }
+    }
+
+    #[test_case(CONFIG; "top level feed tables")]
+    #[test_case("[feeds]\n  # A noisy one\n  [feeds.example]\n  url = \"https://example.com/feed\"\n\n  [feeds.other]\n  url = \"https://other.example.com/feed\"\n"; "indented under a feeds table")]
+    fn test_rename_feed_toml(content: &str) {
+        let renamed = rename_feed_toml(content, "example", "renamed").unwrap();
+        assert_eq!(
+            renamed,
+            content.replace("[feeds.example]", "[feeds.renamed]")
+        );
+        assert!(rename_feed_toml(content, "missing", "renamed").is_err());
+    }
+
+    #[test]
+    fn test_rename_config_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = write_config(dir.path());
+
+        assert!(rename(&config_path, rename_args("missing", "renamed")).is_err());
+        assert!(rename(&config_path, rename_args("example", "Not a slug")).is_err());
+        assert!(rename(&config_path, rename_args("example", "example")).is_err());
+
+        rename(&config_path, rename_args("example", "renamed")).unwrap();
+        let config = Config::from_file(&config_path).unwrap();
+        let slugs: Vec<_> = config.feeds.keys().collect();
+        assert_eq!(slugs, ["renamed"]);
+        let content = std::fs::read_to_string(&config_path).unwrap();
+        assert!(content.contains("# A noisy one\n[feeds.renamed]"));
+        assert!(!dir.path().join("itemData.json").exists());
+    }
+
+    #[test]
+    fn test_rename_with_data_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = write_config(dir.path());
+        let data = r#"[{"slug": "example", "title": "Post"}, {"slug": "other", "title": "Post"}]"#;
+        for name in [
+            "feedData.json",
+            "itemData.json",
+            "newData.json",
+            "likeData.json",
+        ] {
+            std::fs::write(dir.path().join(name), data).unwrap();
+        }
+        std::fs::write(dir.path().join("loveData.json"), "[]").unwrap();
+
+        rename(&config_path, rename_args("example", "renamed")).unwrap();
+        for name in [
+            "feedData.json",
+            "itemData.json",
+            "newData.json",
+            "likeData.json",
+        ] {
+            let content = std::fs::read_to_string(dir.path().join(name)).unwrap();
+            let entries: Vec<Value> = serde_json::from_str(&content).unwrap();
+            let slugs: Vec<_> = entries.iter().map(|entry| &entry["slug"]).collect();
+            assert_eq!(slugs, ["renamed", "other"], "{name}");
+            assert_eq!(entries[0]["title"], "Post");
+        }
+        let content = std::fs::read_to_string(dir.path().join("loveData.json")).unwrap();
+        assert_eq!(content, "[]");
+    }
+}
@@ -1,20 +1,28 @@
-use std::io::BufReader;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::Read;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::channel;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use crate::config::{Config, ParseConfig};
-use crate::FeedInfo;
+use crate::config::{Config, DescriptionSource, ParseConfig};
+use crate::error::FeedError;
+use crate::text_utils::Summarizer;
+use crate::{parse_duration, FeedInfo};
 
-use anyhow::Result;
-use chrono::{DateTime, Utc};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, TimeDelta, Utc};
+use clap::Args;
 use feed_rs::model::Entry;
 use feed_rs::parser;
+use notify::{RecursiveMode, Watcher};
+use rand::Rng;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use regex::Regex;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use ureq::{Agent, AgentBuilder};
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 
 struct FeedOutput {
     #[serde(flatten)]
@@ -23,71 +31,377 @@ struct FeedOutput {
     items: Vec<RssItem>,
 }
 
-#[derive(Clone, Debug, Serialize)]
-struct ItemOutput {
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct ItemOutput {
     #[serde(flatten)]
-    meta: FeedInfo,
-    slug: String,
+    pub(crate) meta: FeedInfo,
+    pub(crate) slug: String,
     #[serde(flatten)]
-    item: RssItem,
+    pub(crate) item: RssItem,
 }
 
-#[derive(Clone, Debug, Serialize)]
-struct RssItem {
-    title: String,
-    item_url: String,
-    description: String,
-    safe_description: String,
-    pub_date: Option<DateTime<Utc>>,
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct RssItem {
+    pub(crate) title: String,
+    pub(crate) item_url: String,
+    pub(crate) description: String,
+    pub(crate) safe_description: String,
+    pub(crate) pub_date: Option<DateTime<Utc>>,
 }
 
-pub fn run(config: Config) -> Result<()> {
-    // A channel for transmitting the results of HTTP requests
+/// Summary of a fetch for deploy pipelines, e.g. to show when the site was
+/// last updated
+#[derive(Debug, Serialize)]
+struct BuildInfo {
+    built_at: DateTime<Utc>,
+    version: &'static str,
+    feed_count: usize,
+    item_count: usize,
+}
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct FetchArgs {
+    /// Skip fetching if the existing data is younger than this, e.g. "30m"
+    #[arg(long, value_parser = parse_duration)]
+    pub max_data_age: Option<Duration>,
+    /// Stop waiting for slow feeds after this long in total, e.g. "60s"
+    #[arg(long, value_parser = parse_duration)]
+    pub deadline: Option<Duration>,
+    /// Write the raw response of each fetched feed to <DIR>/<slug>.xml
+    #[arg(long, value_name = "DIR")]
+    pub save_raw: Option<PathBuf>,
+    /// Drop items published more than this many days ago, overriding the config
+    #[arg(long, value_name = "DAYS")]
+    pub max_age: Option<u64>,
+    /// Succeed and write the data files even if no items were fetched
+    #[arg(long)]
+    pub allow_empty: bool,
+    /// Keep running and fetch again whenever the config file changes
+    #[arg(long)]
+    pub watch: bool,
+}
+
+/// How long the config file has to stay unchanged before we fetch again
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Fetch once, then again with a freshly loaded config every time the config
+/// file changes
+pub fn watch(config: Config, config_path: &Path, args: FetchArgs) -> Result<()> {
+    if let Err(e) = run(config, args.clone()) {
+        log::error!("{e:#}");
+    }
+    // Data written by the previous run is always fresh, but the config isn't
+    let args = FetchArgs {
+        max_data_age: None,
+        ..args
+    };
+    log::info!("Watching {} for changes", config_path.display());
+    watch_file(config_path, WATCH_DEBOUNCE, || {
+        log::info!("{} changed, fetching again", config_path.display());
+        let result = Config::from_file(config_path).and_then(|config| run(config, args.clone()));
+        if let Err(e) = result {
+            log::error!("{e:#}");
+        }
+        ControlFlow::Continue(())
+    })
+}
+
+/// Call `on_change` after every burst of changes to the file at `path`
+fn watch_file<F>(path: &Path, debounce: Duration, mut on_change: F) -> Result<()>
+where
+    F: FnMut() -> ControlFlow<()>,
+{
     let (tx, rx) = channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    // Editors often replace the file rather than write to it, so watch the
+    // directory it is in
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
 
-    // Spin off background thread for parallel URL processing
-    // TODO use async instead
-    thread::spawn(move || {
-        let agent: Agent = AgentBuilder::new()
-            .timeout_read(Duration::from_secs(10))
-            .build();
-        config.feeds.par_iter().for_each(|(slug, feed_info)| {
-            let slug = slug.clone();
-            let feed_info = feed_info.clone();
-            if let Some(feed) = fetch_feed(&agent, &feed_info.url) {
-                println!("Fetched feed for {slug}");
-                tx.send((feed, feed_info, slug)).unwrap();
-            } else {
-                eprintln!("Failed to load feed for {slug}");
-            }
-        });
-    });
+    loop {
+        let event = rx.recv()??;
+        let touches_file = event
+            .paths
+            .iter()
+            .any(|changed| changed.file_name() == path.file_name());
+        if !touches_file || event.kind.is_access() {
+            continue;
+        }
+        while rx.recv_timeout(debounce).is_ok() {}
+        if on_change().is_break() {
+            return Ok(());
+        }
+    }
+}
+
+pub fn run(mut config: Config, args: FetchArgs) -> Result<()> {
+    if let Some(max_data_age) = args.max_data_age {
+        if data_is_fresh(&config, max_data_age) {
+            log::info!("Feed data is younger than {max_data_age:?}, skipping fetch");
+            return Ok(());
+        }
+    }
+    if let Some(max_age) = args.max_age {
+        config.parse_config.max_item_age_days = Some(max_age);
+    }
+    if let Some(save_raw) = &args.save_raw {
+        std::fs::create_dir_all(save_raw)
+            .with_context(|| format!("Failed to create directory: {}", save_raw.display()))?;
+    }
+
+    let fetcher = fetcher(&config.parse_config);
+    run_with_fetcher(config, args, fetcher)
+}
+
+/// A feed response along with where it was finally fetched from
+#[derive(Debug)]
+pub(crate) struct FetchedFeed {
+    pub(crate) status: u16,
+    /// The URL after following any redirects
+    pub(crate) url: String,
+    pub(crate) body: Vec<u8>,
+}
+
+/// Fetch the body of a feed over HTTP as configured
+pub(crate) fn fetcher(
+    parse_config: &ParseConfig,
+) -> impl Fn(&str) -> Result<Vec<u8>, FeedError> + Send + Sync + 'static {
+    let fetch = detailed_fetcher(parse_config);
+    move |url| fetch(url).map(|fetched| fetched.body)
+}
+
+/// Fetch a feed over HTTP as configured, keeping the status and final URL
+pub(crate) fn detailed_fetcher(
+    parse_config: &ParseConfig,
+) -> impl Fn(&str) -> Result<FetchedFeed, FeedError> + Send + Sync + 'static {
+    let mut agent_builder = AgentBuilder::new().timeout_read(Duration::from_secs(10));
+    if let Some(user_agent) = &parse_config.user_agent {
+        agent_builder = agent_builder.user_agent(user_agent);
+    }
+    let agent: Agent = agent_builder.build();
+    let retries = parse_config.fetch_retries;
+    move |url| fetch_feed_with_retry(&agent, url, retries)
+}
+
+fn run_with_fetcher<F>(config: Config, args: FetchArgs, fetcher: F) -> Result<()>
+where
+    F: Fn(&str) -> Result<Vec<u8>, FeedError> + Send + Sync + 'static,
+{
+    let (feeds, disabled): (HashMap<_, _>, HashMap<_, _>) = config
+        .feeds
+        .clone()
+        .into_iter()
+        .partition(|(_, feed_info)| feed_info.is_enabled());
+    for slug in disabled.keys() {
+        log::info!("Skipping feed for {slug}: disabled");
+    }
+    let feed_count = feeds.len();
+    let fetched = fetch_all(feeds, fetcher, args.deadline, args.save_raw);
 
     let re = Regex::new(r"<[^>]*>").unwrap();
 
-    let feed_data: Vec<_> = rx
+    let mut feed_data: Vec<_> = fetched
         .into_iter()
         .map(|(feed, feed_info, slug)| {
-            println!("Building feed for {slug}");
+            log::debug!("Building feed for {slug}");
             build_feed(feed, feed_info, &config.parse_config, &re, slug)
         })
         .collect();
+    let fetched_feeds = feed_data.len();
+    if !disabled.is_empty() {
+        // Disabled feeds keep the items they had when they were last fetched,
+        // as long as they still pass the item limits
+        let previous = previous_feed_data(&config.output_config.feed_data_output_path);
+        feed_data.extend(previous.into_iter().filter_map(|feed| {
+            let feed_info = disabled.get(&feed.slug)?;
+            Some(FeedOutput {
+                meta: feed_info.clone(),
+                items: limit_items(feed.items, &config.parse_config),
+                ..feed
+            })
+        }));
+    }
+    if feed_data.iter().all(|feed| feed.items.is_empty()) && !args.allow_empty {
+        bail!(
+            "No items fetched from {feed_count} feeds, keeping the existing data (use --allow-empty to write it anyway)"
+        );
+    }
 
-    write_data_to_file(&config.output_config.feed_data_output_path, &feed_data);
+    if let Some(max_total_items) = config.parse_config.max_total_items {
+        limit_total_items(&mut feed_data, max_total_items);
+    }
 
     let mut items: Vec<_> = feed_data.iter().flat_map(Vec::<ItemOutput>::from).collect();
     items.sort_unstable_by_key(|io| io.item.pub_date);
     items.reverse();
+
+    write_data_to_file(&config.output_config.feed_data_output_path, &feed_data);
     write_data_to_file(&config.output_config.item_data_output_path, &items);
+    let build_info = BuildInfo {
+        built_at: Utc::now(),
+        version: env!("CARGO_PKG_VERSION"),
+        feed_count: feed_data.len(),
+        item_count: items.len(),
+    };
+    write_data_to_file(&config.output_config.build_info_output_path, &build_info);
+
+    for tier in &config.tiers {
+        let tier_items: Vec<_> = items.iter().filter(|io| &io.meta.tier == tier).collect();
+        let tier_data_output_path =
+            Path::new(&config.output_config.tier_data_output_dir).join(format!("{tier}Data.json"));
+        write_data_to_file(&tier_data_output_path, &tier_items);
+    }
 
-    println!(
-        "Processed {} items from {} feeds",
+    log::info!(
+        "Processed {} items from {} of {} feeds ({} succeeded)",
         items.len(),
-        feed_data.len()
+        fetched_feeds,
+        feed_count,
+        success_rate(fetched_feeds, feed_count)
     );
     Ok(())
 }
 
+/// Apply the age and per feed limits to items built by an earlier fetch
+fn limit_items(mut items: Vec<RssItem>, parse_config: &ParseConfig) -> Vec<RssItem> {
+    if let Some(cutoff) = parse_config.max_item_age_days.and_then(age_cutoff) {
+        items.retain(|item| {
+            item.pub_date
+                .map_or(parse_config.keep_undated_items, |date| date >= cutoff)
+        });
+    }
+    items.truncate(parse_config.max_articles);
+    items
+}
+
+/// Drop all but the newest `max_total_items` items across all feeds
+fn limit_total_items(feed_data: &mut [FeedOutput], max_total_items: usize) {
+    let mut dates: Vec<_> = feed_data
+        .iter()
+        .enumerate()
+        .flat_map(|(feed, feed_output)| {
+            let items = feed_output.items.iter().enumerate();
+            items.map(move |(item, rss_item)| (rss_item.pub_date, feed, item))
+        })
+        .collect();
+    if dates.len() <= max_total_items {
+        return;
+    }
+    // Newest first, undated items last, as in the item data
+    dates.sort_unstable_by_key(|&(pub_date, _, _)| std::cmp::Reverse(pub_date));
+    let keep: HashSet<_> = dates[..max_total_items]
+        .iter()
+        .map(|&(_, feed, item)| (feed, item))
+        .collect();
+    for (feed, feed_output) in feed_data.iter_mut().enumerate() {
+        let mut item = 0;
+        feed_output.items.retain(|_| {
+            item += 1;
+            keep.contains(&(feed, item - 1))
+        });
+    }
+}
+
+/// The feed data written by a previous run, if there is any
+fn previous_feed_data(path: &str) -> Vec<FeedOutput> {
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    serde_json::from_str(&contents).unwrap_or_else(|e| {
+        log::warn!(
+            "Failed to read previous feed data from {path}, disabled feeds lose their items: {e}"
+        );
+        Vec::new()
+    })
+}
+
+/// Percentage of `succeeded` out of `total`, rounded to the nearest whole number
+fn success_rate(succeeded: usize, total: usize) -> String {
+    if total == 0 {
+        return "N/A".to_string();
+    }
+    format!("{:.0}%", succeeded as f64 * 100.0 / total as f64)
+}
+
+/// Fetch all feeds in parallel, giving up on feeds still in flight once the
+/// deadline has passed
+fn fetch_all<F>(
+    feeds: HashMap<String, FeedInfo>,
+    fetcher: F,
+    deadline: Option<Duration>,
+    save_raw: Option<PathBuf>,
+) -> Vec<(feed_rs::model::Feed, FeedInfo, String)>
+where
+    F: Fn(&str) -> Result<Vec<u8>, FeedError> + Send + Sync + 'static,
+{
+    // A deadline too far off to represent is as good as none
+    let deadline = deadline.and_then(|deadline| Instant::now().checked_add(deadline));
+    let mut in_flight: BTreeSet<String> = feeds.keys().cloned().collect();
+
+    // A channel for transmitting the results of HTTP requests
+    let (tx, rx) = channel();
+
+    // Spin off background thread for parallel URL processing
+    // TODO use async instead
+    thread::spawn(move || {
+        feeds.par_iter().for_each(|(slug, feed_info)| {
+            let body = fetcher(&feed_info.url);
+            if let (Ok(body), Some(save_raw)) = (&body, &save_raw) {
+                save_raw_feed(save_raw, slug, body);
+            }
+            let feed = body.and_then(|body| parse_feed(&body));
+            // The receiver is gone if the deadline has passed
+            let _ = tx.send((feed, feed_info.clone(), slug.clone()));
+        });
+    });
+
+    let mut fetched = Vec::new();
+    loop {
+        let result = match deadline {
+            Some(deadline) => {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                rx.recv_timeout(timeout).map_err(|_| ())
+            }
+            None => rx.recv().map_err(|_| ()),
+        };
+        // Either all feeds are done or we ran out of time
+        let Ok((feed, feed_info, slug)) = result else {
+            break;
+        };
+        in_flight.remove(&slug);
+        match feed {
+            Ok(feed) => {
+                log::debug!(
+                    "Fetched feed for {slug} with {} entries",
+                    feed.entries.len()
+                );
+                fetched.push((feed, feed_info, slug));
+            }
+            Err(e) => log::warn!("Failed to load feed for {slug}: {e}"),
+        }
+    }
+    for slug in in_flight {
+        log::warn!(
+            "Failed to load feed for {slug}: {} (deadline)",
+            FeedError::Timeout
+        );
+    }
+    fetched
+}
+
+/// Whether the item data written by a previous run is younger than `max_age`
+pub fn data_is_fresh(config: &Config, max_age: Duration) -> bool {
+    let item_data_output_path = &config.output_config.item_data_output_path;
+    std::fs::metadata(item_data_output_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age < max_age)
+}
+
 impl From<&FeedOutput> for Vec<ItemOutput> {
     fn from(feed: &FeedOutput) -> Self {
         feed.items
@@ -100,16 +414,76 @@ impl From<&FeedOutput> for Vec<ItemOutput> {
             .collect::<Vec<_>>()
     }
 }
-fn write_data_to_file<D: Serialize>(output_path: &str, data: &D) {
+fn write_data_to_file<P: AsRef<Path>, D: Serialize>(output_path: P, data: &D) {
     let contents = serde_json::to_string_pretty(data).unwrap();
     std::fs::write(output_path, contents).expect("Unable to write file");
 }
 
-fn fetch_feed(agent: &Agent, url: &str) -> Option<feed_rs::model::Feed> {
-    let response = agent.get(url).call().ok()?;
-    let reader = BufReader::new(response.into_reader());
-    parser::parse(reader).ok()
+fn save_raw_feed(dir: &Path, slug: &str, body: &[u8]) {
+    let file_name: String = slug
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect();
+    let path = dir.join(format!("{file_name}.xml"));
+    if let Err(e) = std::fs::write(&path, body) {
+        log::warn!(
+            "Failed to save raw feed for {slug} to {}: {e}",
+            path.display()
+        );
+    }
 }
+
+/// Fetch a feed, retrying transient failures with exponential backoff
+fn fetch_feed_with_retry(agent: &Agent, url: &str, retries: u32) -> Result<FetchedFeed, FeedError> {
+    let mut rng = rand::thread_rng();
+    let mut attempt = 0;
+    loop {
+        match fetch_feed(agent, url) {
+            Err(e) if attempt < retries && e.is_transient() => {
+                let delay = backoff_delay(attempt, &mut rng);
+                log::debug!("Retrying {url} in {delay:?} after error: {e}");
+                thread::sleep(delay);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Exponential backoff with random jitter, so that feeds from the same host
+/// which failed together don't all retry at the same moment
+fn backoff_delay(attempt: u32, rng: &mut impl Rng) -> Duration {
+    let base = 100 * (1 << attempt.min(10));
+    Duration::from_millis(rng.gen_range(base / 2..=base))
+}
+
+fn fetch_feed(agent: &Agent, url: &str) -> Result<FetchedFeed, FeedError> {
+    let response = agent.get(url).call()?;
+    let status = response.status();
+    let url = response.get_url().to_string();
+    let mut body = Vec::new();
+    response.into_reader().read_to_end(&mut body)?;
+    if body.iter().all(u8::is_ascii_whitespace) {
+        return Err(FeedError::Empty);
+    }
+    Ok(FetchedFeed { status, url, body })
+}
+
+pub(crate) fn parse_feed(body: &[u8]) -> Result<feed_rs::model::Feed, FeedError> {
+    parser::parse(body).or_else(|e| {
+        if std::str::from_utf8(body).is_ok() {
+            return Err(e.into());
+        }
+        // A single bad byte shouldn't cost us the whole feed
+        log::debug!("Retrying with invalid UTF-8 replaced after error: {e}");
+        let decoded = String::from_utf8_lossy(body);
+        Ok(parser::parse(decoded.as_bytes())?)
+    })
+}
+
 fn build_feed(
     feed: feed_rs::model::Feed,
     feed_info: FeedInfo,
@@ -117,11 +491,21 @@ fn build_feed(
     re: &Regex,
     slug: String,
 ) -> FeedOutput {
-    let items = feed
-        .entries
+    let mut entries = feed.entries;
+    if let Some(cutoff) = parse_config.max_item_age_days.and_then(age_cutoff) {
+        entries.retain(|entry| {
+            entry_date(entry).map_or(parse_config.keep_undated_items, |date| date >= cutoff)
+        });
+    }
+    // Feeds may list entries oldest first, so make sure we keep the newest.
+    // Without dates for every entry, the source order is the best we have.
+    if entries.iter().all(|entry| entry_date(entry).is_some()) {
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry_date(entry)));
+    }
+    let items = entries
         .into_iter()
         .take(parse_config.max_articles)
-        .map(|entry| build_item(entry, re, parse_config.description_max_words))
+        .map(|entry| build_item(entry, re, parse_config))
         .collect();
     FeedOutput {
         meta: feed_info,
@@ -130,16 +514,28 @@ fn build_feed(
     }
 }
 
-fn build_item(entry: feed_rs::model::Entry, re: &Regex, description_max_words: usize) -> RssItem {
+/// The oldest date an item may have to be kept, or `None` if the age reaches
+/// further back than a date can represent
+fn age_cutoff(max_item_age_days: u64) -> Option<DateTime<Utc>> {
+    let max_age = TimeDelta::try_days(max_item_age_days.try_into().ok()?)?;
+    Utc::now().checked_sub_signed(max_age)
+}
+
+fn build_item(entry: feed_rs::model::Entry, re: &Regex, parse_config: &ParseConfig) -> RssItem {
     let title = entry.title.clone().map(|t| t.content).unwrap_or_default();
     let item_url = entry
         .links
         .first()
         .map_or(String::new(), |link| link.href.clone());
-    let pub_date = entry.published.or(entry.updated);
-    let description = get_description_from_entry(entry).unwrap_or_default();
-    let description = get_short_description(description, description_max_words);
-    let safe_description = re.replace_all(&description, "").to_string();
+    let pub_date = entry_date(&entry);
+    let description = get_description_from_entry(&entry, &parse_config.description_source_order)
+        .unwrap_or_default();
+    let summarizer = Summarizer::new(
+        parse_config.description_max_words,
+        &parse_config.truncation_suffix,
+    );
+    let safe_description = summarizer.summarize(&re.replace_all(&description, ""));
+    let description = summarizer.summarize(&description);
 
     RssItem {
         title,
@@ -150,34 +546,40 @@ fn build_item(entry: feed_rs::model::Entry, re: &Regex, description_max_words: u
     }
 }
 
-fn get_description_from_entry(entry: Entry) -> Option<String> {
-    // Try in the following order
-    // 1. Summary
-    // 2. Content
-    // 3. Media description
-    if let Some(summary) = entry.summary {
-        return Some(summary.content);
-    }
-    if let Some(content) = entry.content {
-        return content.body;
-    }
-    if let Some(media) = entry.media.first() {
-        if let Some(description) = &media.description {
-            return Some(description.content.clone());
-        }
-    }
-    None
+pub(crate) fn entry_date(entry: &Entry) -> Option<DateTime<Utc>> {
+    entry.published.or(entry.updated)
 }
 
-fn get_short_description(description: String, max_words: usize) -> String {
-    description
-        .split_whitespace()
-        .take(max_words)
-        .collect::<Vec<_>>()
-        .join(" ")
+/// The description from the first source in `order` that has one. Order
+/// beats length, so that e.g. a clean summary wins over content full of ads
+fn get_description_from_entry(entry: &Entry, order: &[DescriptionSource]) -> Option<String> {
+    order.iter().find_map(|source| match source {
+        DescriptionSource::Summary => entry
+            .summary
+            .as_ref()
+            .map(|summary| summary.content.clone()),
+        DescriptionSource::Content => entry
+            .content
+            .as_ref()
+            .and_then(|content| content.body.clone()),
+        // Feeds like YouTube's nest descriptions in media:group, possibly
+        // several per entry, so use the longest
+        DescriptionSource::Media => entry
+            .media
+            .iter()
+            .filter_map(|media| media.description.as_ref())
+            .map(|description| description.content.trim())
+            .filter(|content| !content.is_empty())
+            .max_by_key(|content| content.len())
+            .map(str::to_string),
+    })
 }
+
 #[cfg(test)]
 mod tests {
+    use std::io::Write;
+    use std::net::TcpListener;
+
     use super::*;
     use test_case::test_case;
 
@@ -185,11 +587,553 @@ mod tests {
         include_str!("../test_data/youtube.xml"),
         include_str!("../test_data/atlassian.xml"),
         include_str!("../test_data/xeiaso.rss"),
+        include_str!("../test_data/oldest_first.rss"),
+        include_str!("../test_data/jsonfeed.json"),
     ];
 
+    fn test_feed_info(url: &str) -> FeedInfo {
+        FeedInfo {
+            url: url.to_string(),
+            author: "Example Author".to_string(),
+            tier: "new".parse().unwrap(),
+            enabled: None,
+        }
+    }
+
+    #[test]
+    fn test_deadline_cuts_off_slow_feeds() {
+        let feeds = HashMap::from([
+            ("fast".to_string(), test_feed_info("fast")),
+            ("slow".to_string(), test_feed_info("slow")),
+        ]);
+        let fetcher = |url: &str| {
+            if url == "slow" {
+                thread::sleep(Duration::from_secs(1));
+            }
+            Ok(TEST_DATA[2].as_bytes().to_vec())
+        };
+
+        let start = Instant::now();
+        let fetched = fetch_all(feeds, fetcher, Some(Duration::from_millis(200)), None);
+        assert!(start.elapsed() < Duration::from_millis(800));
+        // Depending on the thread count the fast feed may be queued behind the slow one
+        assert!(fetched.iter().all(|(_, _, slug)| slug == "fast"));
+    }
+
+    /// The default config with all output going to `dir`
+    #[test]
+    fn test_unrepresentable_deadline() {
+        let feeds = HashMap::from([("fast".to_string(), test_feed_info("fast"))]);
+        let fetcher = |_: &str| Ok(TEST_DATA[2].as_bytes().to_vec());
+        let fetched = fetch_all(feeds, fetcher, Some(Duration::MAX), None);
+        assert_eq!(fetched.len(), 1);
+    }
+
+    fn temp_config(dir: &Path) -> Config {
+        let mut config = Config::default();
+        let path = |p: &str| dir.join(p).to_string_lossy().into_owned();
+        config.output_config.feed_data_output_path = path("feedData.json");
+        config.output_config.item_data_output_path = path("itemData.json");
+        config.output_config.tier_data_output_dir = path("");
+        config.output_config.build_info_output_path = path("build-info.json");
+        config
+    }
+
+    #[test]
+    fn test_backoff_delay_jitter() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(42);
+        for attempt in 0..4 {
+            let base = 100 << attempt;
+            let delays: Vec<_> = (0..50).map(|_| backoff_delay(attempt, &mut rng)).collect();
+            assert!(delays
+                .iter()
+                .all(|delay| (base / 2..=base).contains(&(delay.as_millis() as u64))));
+            assert!(
+                delays.iter().any(|delay| *delay != delays[0]),
+                "Delays are jittered"
+            );
+        }
+    }
+
+    #[test]
+    fn test_watch_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("spacefeeder.toml");
+        std::fs::write(&config_path, "max_articles = 5").unwrap();
+
+        // Watch on another thread, so that a missed change fails the test
+        // rather than hanging it
+        let (tx, rx) = channel();
+        let watched_path = config_path.clone();
+        thread::spawn(move || {
+            let mut reloads = 0;
+            let result = watch_file(&watched_path, Duration::from_millis(50), || {
+                reloads += 1;
+                ControlFlow::Break(())
+            });
+            let _ = tx.send(result.map(|()| reloads));
+        });
+
+        thread::sleep(Duration::from_millis(200));
+        std::fs::write(dir.path().join("unrelated.toml"), "").unwrap();
+        std::fs::write(&config_path, "max_articles = 10").unwrap();
+        let reloads = rx
+            .recv_timeout(Duration::from_secs(10))
+            .expect("Watcher did not notice the change in time");
+        assert_eq!(reloads.unwrap(), 1);
+        let config = std::fs::read_to_string(&config_path).unwrap();
+        assert_eq!(config, "max_articles = 10");
+    }
+
+    #[test]
+    fn test_build_info() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = temp_config(dir.path());
+        let fetcher = |_: &str| Ok(TEST_DATA[2].as_bytes().to_vec());
+        run_with_fetcher(config, FetchArgs::default(), fetcher).unwrap();
+
+        let build_info = std::fs::read_to_string(dir.path().join("build-info.json")).unwrap();
+        let build_info: serde_json::Value = serde_json::from_str(&build_info).unwrap();
+        assert_eq!(build_info["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(build_info["feed_count"], 1);
+        assert_eq!(build_info["item_count"], 5);
+        assert!(build_info["built_at"]
+            .as_str()
+            .is_some_and(|built_at| built_at.parse::<DateTime<Utc>>().is_ok()));
+    }
+
+    #[test]
+    fn test_disabled_feed_is_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = temp_config(dir.path());
+        config.feeds.insert(
+            "enabled".to_string(),
+            test_feed_info("https://enabled.example.com/feed"),
+        );
+        let fetcher = |_: &str| Ok(TEST_DATA[2].as_bytes().to_vec());
+        run_with_fetcher(config, FetchArgs::default(), fetcher).unwrap();
+
+        let mut config = temp_config(dir.path());
+        config.feeds.get_mut("example").unwrap().enabled = Some(false);
+        config.feeds.insert(
+            "enabled".to_string(),
+            test_feed_info("https://enabled.example.com/feed"),
+        );
+        let fetcher = |url: &str| {
+            assert_eq!(url, "https://enabled.example.com/feed");
+            Ok(TEST_DATA[2].as_bytes().to_vec())
+        };
+        run_with_fetcher(config, FetchArgs::default(), fetcher).unwrap();
+
+        let slugs = |file: &str| {
+            let data = std::fs::read_to_string(dir.path().join(file)).unwrap();
+            let data: Vec<serde_json::Value> = serde_json::from_str(&data).unwrap();
+            let mut slugs: Vec<_> = data
+                .iter()
+                .map(|entry| entry["slug"].as_str().unwrap().to_string())
+                .collect();
+            slugs.sort();
+            slugs.dedup();
+            slugs
+        };
+        // The disabled feed wasn't fetched, but its old items are still there
+        assert_eq!(slugs("feedData.json"), ["enabled", "example"]);
+        assert_eq!(slugs("itemData.json"), ["enabled", "example"]);
+        assert_eq!(slugs("newData.json"), ["enabled", "example"]);
+    }
+
+    #[test]
+    fn test_disabled_feed_items_are_limited() {
+        let dir = tempfile::tempdir().unwrap();
+        let fetcher = |_: &str| Ok(TEST_DATA[2].as_bytes().to_vec());
+        run_with_fetcher(temp_config(dir.path()), FetchArgs::default(), fetcher).unwrap();
+
+        // The only other feed is empty, so all items are the disabled feed's
+        let disabled_config = || {
+            let mut config = temp_config(dir.path());
+            config.feeds.get_mut("example").unwrap().enabled = Some(false);
+            config
+                .feeds
+                .insert("empty".to_string(), test_feed_info("empty"));
+            config
+        };
+        let fetcher = |_: &str| {
+            Ok(br#"<?xml version="1.0"?><rss version="2.0"><channel><title>Empty</title></channel></rss>"#.to_vec())
+        };
+        let item_counts = || {
+            let feed_data = previous_feed_data(&dir.path().join("feedData.json").to_string_lossy());
+            let mut counts: Vec<_> = feed_data
+                .iter()
+                .map(|feed| (feed.slug.clone(), feed.items.len()))
+                .collect();
+            counts.sort();
+            counts
+        };
+
+        let mut config = disabled_config();
+        config.parse_config.max_articles = 2;
+        run_with_fetcher(config, FetchArgs::default(), fetcher).unwrap();
+        assert_eq!(
+            item_counts(),
+            [("empty".to_string(), 0), ("example".to_string(), 2)]
+        );
+
+        // Once its items are too old there is nothing left to write
+        let mut config = disabled_config();
+        config.parse_config.max_item_age_days = Some(1);
+        assert!(run_with_fetcher(config, FetchArgs::default(), fetcher).is_err());
+        let mut config = disabled_config();
+        config.parse_config.max_item_age_days = Some(1);
+        let args = FetchArgs {
+            allow_empty: true,
+            ..FetchArgs::default()
+        };
+        run_with_fetcher(config, args, fetcher).unwrap();
+        assert_eq!(
+            item_counts(),
+            [("empty".to_string(), 0), ("example".to_string(), 0)]
+        );
+    }
+
+    #[test]
+    fn test_max_total_items() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = temp_config(dir.path());
+        config.parse_config.max_total_items = Some(4);
+        config.feeds = ["a", "b", "c"]
+            .into_iter()
+            .map(|slug| (slug.to_string(), test_feed_info(slug)))
+            .collect();
+        // Feed "a" has the newest items, "c" the oldest
+        let fetcher = |url: &str| {
+            let days_ago = match url {
+                "a" => 1,
+                "b" => 10,
+                _ => 100,
+            };
+            let items: String = (0..3)
+                .map(|i| {
+                    let date = Utc::now() - chrono::Duration::days(days_ago + i);
+                    format!(
+                        "<item><title>{url}{i}</title><link>https://example.com/</link><pubDate>{}</pubDate></item>",
+                        date.to_rfc2822()
+                    )
+                })
+                .collect();
+            let feed_xml = format!(
+                r#"<?xml version="1.0"?><rss version="2.0"><channel><title>{url}</title>{items}</channel></rss>"#
+            );
+            Ok(feed_xml.into_bytes())
+        };
+        run_with_fetcher(config, FetchArgs::default(), fetcher).unwrap();
+
+        let item_data = std::fs::read_to_string(dir.path().join("itemData.json")).unwrap();
+        let item_data: serde_json::Value = serde_json::from_str(&item_data).unwrap();
+        let titles: Vec<_> = item_data
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["title"].as_str().unwrap())
+            .collect();
+        assert_eq!(titles, ["a0", "a1", "a2", "b0"]);
+
+        // The feed data and build info agree with the item data
+        let feed_data = std::fs::read_to_string(dir.path().join("feedData.json")).unwrap();
+        let feed_data: Vec<serde_json::Value> = serde_json::from_str(&feed_data).unwrap();
+        let mut item_counts: Vec<_> = feed_data
+            .iter()
+            .map(|feed| {
+                let slug = feed["slug"].as_str().unwrap();
+                (slug, feed["items"].as_array().unwrap().len())
+            })
+            .collect();
+        item_counts.sort();
+        assert_eq!(item_counts, [("a", 3), ("b", 1), ("c", 0)]);
+        let build_info = std::fs::read_to_string(dir.path().join("build-info.json")).unwrap();
+        let build_info: serde_json::Value = serde_json::from_str(&build_info).unwrap();
+        assert_eq!(build_info["item_count"], 4);
+    }
+
+    #[test_case(false; "empty is an error")]
+    #[test_case(true; "empty allowed")]
+    fn test_no_items(allow_empty: bool) {
+        let dir = tempfile::tempdir().unwrap();
+        let config = temp_config(dir.path());
+        let args = FetchArgs {
+            allow_empty,
+            ..Default::default()
+        };
+        let fetcher = |_: &str| Ok(EMPTY_FEED.as_bytes().to_vec());
+
+        let result = run_with_fetcher(config, args, fetcher);
+        assert_eq!(result.is_ok(), allow_empty);
+        assert_eq!(dir.path().join("itemData.json").exists(), allow_empty);
+    }
+
+    const EMPTY_FEED: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+  <channel>
+    <title>Empty</title>
+    <link>https://example.com/</link>
+    <description>A feed without items</description>
+  </channel>
+</rss>"#;
+
+    /// Serve a single connection with `response`, returning the URL to request
+    fn serve_once(response: &'static [u8]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request);
+            let _ = stream.write_all(response);
+            // Hold the connection open for the timeout case
+            thread::sleep(Duration::from_secs(1));
+        });
+        format!("http://{addr}/feed.xml")
+    }
+
+    /// An address nothing is listening on
+    fn closed_addr() -> std::net::SocketAddr {
+        TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_fetch_follows_redirects() {
+        let agent = AgentBuilder::new().build();
+        let target = serve_once(b"HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\n<rss/>");
+        let redirect = format!(
+            "HTTP/1.1 301 Moved Permanently\r\nLocation: {target}\r\nContent-Length: 0\r\n\r\n"
+        );
+        let url = serve_once(Box::leak(redirect.into_bytes().into_boxed_slice()));
+        let fetched = fetch_feed(&agent, &url).unwrap();
+        assert_eq!((fetched.status, fetched.url), (200, target));
+        assert_eq!(fetched.body, b"<rss/>");
+    }
+
+    #[test]
+    fn test_feed_errors() {
+        let agent = AgentBuilder::new()
+            .timeout_read(Duration::from_millis(200))
+            .build();
+
+        let url = format!("http://{}/feed.xml", closed_addr());
+        let result = fetch_feed(&agent, &url);
+        assert!(matches!(result, Err(FeedError::Network(_))), "{result:?}");
+
+        let url = serve_once(b"HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\n\r\n");
+        let result = fetch_feed(&agent, &url);
+        assert!(matches!(result, Err(FeedError::Status(500))), "{result:?}");
+
+        let url = serve_once(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n");
+        let result = fetch_feed(&agent, &url);
+        assert!(matches!(result, Err(FeedError::Status(403))), "{result:?}");
+
+        let url = serve_once(
+            b"HTTP/1.1 403 Forbidden\r\ncf-mitigated: challenge\r\nContent-Length: 0\r\n\r\n",
+        );
+        let result = fetch_feed(&agent, &url);
+        assert!(matches!(result, Err(FeedError::Blocked(403))), "{result:?}");
+
+        let url = serve_once(
+            b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 34\r\n\r\n<title>Just a moment...</title>...",
+        );
+        let result = fetch_feed(&agent, &url);
+        assert!(matches!(result, Err(FeedError::Blocked(503))), "{result:?}");
+
+        let url = serve_once(b"");
+        let result = fetch_feed(&agent, &url);
+        assert!(matches!(result, Err(FeedError::Timeout)), "{result:?}");
+
+        let url = serve_once(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+        let result = fetch_feed(&agent, &url);
+        assert!(matches!(result, Err(FeedError::Empty)), "{result:?}");
+
+        let url = serve_once(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n");
+        let result = fetch_feed(&agent, &url);
+        assert!(matches!(result, Err(FeedError::Decode(_))), "{result:?}");
+
+        let result = parse_feed(b"<html><body>Not a feed</body></html>");
+        assert!(matches!(result, Err(FeedError::Parse(_))), "{result:?}");
+    }
+
+    #[test]
+    fn test_save_raw() {
+        let dir = tempfile::tempdir().unwrap();
+        let feeds = HashMap::from([
+            ("good".to_string(), test_feed_info("good")),
+            ("bad/../slug".to_string(), test_feed_info("good")),
+            ("unreachable".to_string(), test_feed_info("unreachable")),
+        ]);
+        let fetcher = |url: &str| match url {
+            "good" => Ok(TEST_DATA[0].as_bytes().to_vec()),
+            _ => Err(FeedError::Empty),
+        };
+
+        let fetched = fetch_all(feeds, fetcher, None, Some(dir.path().to_path_buf()));
+        assert_eq!(fetched.len(), 2);
+        let mut files: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        assert_eq!(files, ["bad____slug.xml", "good.xml"]);
+        let saved = std::fs::read_to_string(dir.path().join("good.xml")).unwrap();
+        assert_eq!(saved, TEST_DATA[0]);
+    }
+
+    #[test]
+    fn test_data_is_fresh() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        let item_data_output_path = dir.path().join("itemData.json");
+        config.output_config.item_data_output_path =
+            item_data_output_path.to_string_lossy().into_owned();
+
+        assert!(!data_is_fresh(&config, Duration::from_secs(3600)));
+        std::fs::write(&item_data_output_path, "[]").unwrap();
+        assert!(data_is_fresh(&config, Duration::from_secs(3600)));
+        assert!(!data_is_fresh(&config, Duration::ZERO));
+    }
+
+    #[test]
+    fn test_oldest_first_feed_keeps_newest() {
+        let feed = parser::parse(TEST_DATA[3].as_bytes()).unwrap();
+        let re = Regex::new(r"<[^>]*>").unwrap();
+        let mut config = Config::default();
+        config.parse_config.max_articles = 2;
+        let (slug, feed_info) = config.feeds.into_iter().next().unwrap();
+        let feed_data = build_feed(feed, feed_info, &config.parse_config, &re, slug);
+        let titles: Vec<_> = feed_data
+            .items
+            .iter()
+            .map(|item| item.title.as_str())
+            .collect();
+        assert_eq!(titles, ["Fourth post", "Third post"]);
+    }
+
+    #[test_case(true, &["Recent post", "Undated post"]; "keep undated")]
+    #[test_case(false, &["Recent post"]; "drop undated")]
+    fn test_max_item_age(keep_undated_items: bool, expected: &[&str]) {
+        let item = |title: &str, days_ago: Option<i64>| {
+            let pub_date = days_ago.map_or(String::new(), |days| {
+                let date = Utc::now() - chrono::Duration::days(days);
+                format!("<pubDate>{}</pubDate>", date.to_rfc2822())
+            });
+            format!(
+                "<item><title>{title}</title><link>https://example.com/</link>{pub_date}</item>"
+            )
+        };
+        let feed_xml = format!(
+            r#"<?xml version="1.0"?><rss version="2.0"><channel><title>Mixed</title>{}{}{}</channel></rss>"#,
+            item("Recent post", Some(3)),
+            item("Old post", Some(400)),
+            item("Undated post", None),
+        );
+        let feed = parser::parse(feed_xml.as_bytes()).unwrap();
+        let re = Regex::new(r"<[^>]*>").unwrap();
+        let mut config = Config::default();
+        config.parse_config.max_item_age_days = Some(30);
+        config.parse_config.keep_undated_items = keep_undated_items;
+        let (slug, feed_info) = config.feeds.into_iter().next().unwrap();
+        let feed_data = build_feed(feed, feed_info, &config.parse_config, &re, slug);
+        let titles: Vec<_> = feed_data
+            .items
+            .iter()
+            .map(|item| item.title.as_str())
+            .collect();
+        assert_eq!(titles, expected);
+    }
+
+    #[test_case(30, true; "in range")]
+    #[test_case(100_000_000, false; "before the earliest date")]
+    #[test_case(u64::MAX, false; "too many days")]
+    fn test_age_cutoff(max_item_age_days: u64, has_cutoff: bool) {
+        assert_eq!(age_cutoff(max_item_age_days).is_some(), has_cutoff);
+    }
+
+    #[test_case(&[DescriptionSource::Summary, DescriptionSource::Content], "Clean summary"; "summary first")]
+    #[test_case(&[DescriptionSource::Content, DescriptionSource::Summary], "Content with ads"; "content first")]
+    #[test_case(&[DescriptionSource::Media, DescriptionSource::Content], "Content with ads"; "falls through missing media")]
+    #[test_case(&[], ""; "no sources")]
+    fn test_description_source_order(order: &[DescriptionSource], expected: &str) {
+        let feed_xml = r#"<?xml version="1.0"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Both</title>
+  <entry>
+    <title>Post</title>
+    <summary>Clean summary</summary>
+    <content type="html">Content with ads</content>
+  </entry>
+</feed>"#;
+        let feed = parser::parse(feed_xml.as_bytes()).unwrap();
+        let entry = feed.entries.into_iter().next().unwrap();
+        let description = get_description_from_entry(&entry, order);
+        assert_eq!(description.unwrap_or_default(), expected);
+    }
+
+    #[test_case(0, 0, "N/A"; "no feeds")]
+    #[test_case(0, 4, "0%"; "all failed")]
+    #[test_case(2, 3, "67%"; "rounded")]
+    #[test_case(19, 20, "95%"; "partial")]
+    #[test_case(5, 5, "100%"; "all succeeded")]
+    fn test_success_rate(succeeded: usize, total: usize, expected: &str) {
+        assert_eq!(success_rate(succeeded, total), expected);
+    }
+
+    #[test]
+    fn test_youtube_media_description() {
+        let feed = parser::parse(TEST_DATA[0].as_bytes()).unwrap();
+        let entry = &feed.entries[0];
+        assert!(entry.summary.is_none() && entry.content.is_none());
+        let description = get_description_from_entry(entry, &[DescriptionSource::Media]);
+        assert!(description.is_some_and(|description| !description.is_empty()));
+    }
+
+    #[test]
+    fn test_json_feed() {
+        let feed = parse_feed(TEST_DATA[4].as_bytes()).unwrap();
+        let re = Regex::new(r"<[^>]*>").unwrap();
+        let config = Config::default();
+        let (slug, feed_info) = config.feeds.into_iter().next().unwrap();
+        let feed_data = build_feed(feed, feed_info, &config.parse_config, &re, slug);
+        let item = &feed_data.items[0];
+        assert_eq!(item.title, "Post 6");
+        assert_eq!(item.item_url, "https://example.org/posts/6");
+        assert_eq!(item.description, "Summary of post 6.");
+        assert!(item.pub_date.is_some());
+    }
+
+    #[test]
+    fn test_malformed_xml() {
+        let result = parse_feed(b"<rss version=\"2.0\"><channel><title>Broken</channel></rss>");
+        let Err(e @ FeedError::Parse(_)) = result else {
+            panic!("Expected a parse error, got {result:?}");
+        };
+        assert!(e.to_string().contains("unable to parse XML"), "{e}");
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let mut bytes = TEST_DATA[2].as_bytes().to_vec();
+        let link_pos = bytes.windows(6).position(|w| w == b"<link>").unwrap() + 6;
+        bytes.splice(link_pos..link_pos, [0xff, 0xfe]);
+        assert!(std::str::from_utf8(&bytes).is_err());
+        assert!(parser::parse(bytes.as_slice()).is_err());
+        let feed = parse_feed(&bytes);
+        assert!(feed.is_ok(), "Feed parsed despite invalid UTF-8");
+    }
+
     #[test_case(TEST_DATA[0]; "Import youtube video feed")]
     #[test_case(TEST_DATA[1]; "Import atlassian feed")]
     #[test_case(TEST_DATA[2]; "Import Xe Iaso feed")]
+    #[test_case(TEST_DATA[4]; "Import JSON feed")]
     fn test_feed(feed_xml: &str) {
         let feed = parser::parse(feed_xml.as_bytes());
         assert!(feed.is_ok(), "Feed parsed correctly");
@@ -1,6 +1,8 @@
 use std::time::Duration;
 
 use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use serde::Serialize;
 use ureq::AgentBuilder;
 use url::Url;
 
@@ -16,18 +18,38 @@ const LIKELY_PATHS: &[&str] = &[
     ".atom",
 ];
 
-pub fn run(base_url: &str) -> Result<String> {
+/// The outcome of looking for a feed, including every URL that was tried
+#[derive(Debug, Serialize)]
+pub struct Discovery {
+    pub found: Option<String>,
+    pub tried: Vec<String>,
+    /// How the feed was recognised, only set if one was found
+    pub method: Option<&'static str>,
+}
+
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Just the feed URL
+    #[default]
+    Text,
+    /// A JSON object with the feed URL and every URL tried
+    Json,
+}
+
+pub fn run(base_url: &str) -> Result<Discovery> {
     let base_url = Url::parse(base_url)?;
     let agent = AgentBuilder::new()
         .timeout_read(Duration::from_secs(3))
         .build();
 
-    let rss_path = LIKELY_PATHS.iter().find_map(|&path| {
+    let mut tried = Vec::new();
+    let found = LIKELY_PATHS.iter().find_map(|&path| {
         let url_to_try = base_url
             .join(path)
             .expect("Already verified URL combined with known good pattern");
         let url_str = url_to_try.as_str();
-        println!("Trying {url_str}");
+        log::info!("Trying {url_str}");
+        tried.push(url_str.to_string());
         if let Ok(res) = agent.head(url_str).call() {
             if is_feed_content_type(res.header("content-type")) {
                 return Some(url_to_try.to_string());
@@ -35,7 +57,19 @@ pub fn run(base_url: &str) -> Result<String> {
         }
         None
     });
-    rss_path.ok_or(anyhow!("Did not find a suitable feed URL"))
+    Ok(Discovery {
+        method: found.as_ref().map(|_| "head"),
+        found,
+        tried,
+    })
+}
+
+impl Discovery {
+    /// The feed URL, or an error if none was found
+    pub fn into_url(self) -> Result<String> {
+        self.found
+            .ok_or(anyhow!("Did not find a suitable feed URL"))
+    }
 }
 
 fn is_feed_content_type(content_type_header: Option<&str>) -> bool {
@@ -54,3 +88,56 @@ fn is_feed_content_type(content_type_header: Option<&str>) -> bool {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+
+    /// Serve `feed_path` as an RSS feed and everything else as not found
+    fn serve_site(feed_path: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = [0; 1024];
+                let len = stream.read(&mut request).unwrap();
+                let request = String::from_utf8_lossy(&request[..len]);
+                let response = if request.starts_with(&format!("HEAD {feed_path} ")) {
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/rss+xml\r\nConnection: close\r\n\r\n"
+                } else {
+                    "HTTP/1.1 404 Not Found\r\nConnection: close\r\n\r\n"
+                };
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        format!("http://{addr}/")
+    }
+
+    #[test]
+    fn test_discovery_json() {
+        let base_url = serve_site("/rss");
+        let discovery = run(&base_url).unwrap();
+        let json = serde_json::to_value(&discovery).unwrap();
+        assert_eq!(json["found"], format!("{base_url}rss"));
+        assert_eq!(json["method"], "head");
+        assert_eq!(
+            json["tried"],
+            serde_json::json!([
+                base_url,
+                format!("{base_url}feed"),
+                format!("{base_url}rss")
+            ])
+        );
+
+        let base_url = serve_site("/nowhere");
+        let discovery = run(&base_url).unwrap();
+        assert_eq!(discovery.tried.len(), LIKELY_PATHS.len());
+        let json = serde_json::to_value(&discovery).unwrap();
+        assert!(json["found"].is_null() && json["method"].is_null());
+    }
+}
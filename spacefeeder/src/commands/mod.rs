@@ -1,2 +1,6 @@
+pub mod clean;
+pub mod edit_feed;
 pub mod fetch_feeds;
 pub mod find_feed;
+pub mod stats;
+pub mod validate;
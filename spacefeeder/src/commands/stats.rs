@@ -0,0 +1,201 @@
+use std::cmp::Reverse;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, TimeDelta, Utc};
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+
+use crate::commands::fetch_feeds::ItemOutput;
+use crate::config::Config;
+
+#[derive(Args, Debug)]
+pub struct StatsArgs {
+    /// How to print the stats
+    #[arg(long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// One line per feed
+    #[default]
+    Text,
+    /// A JSON array with the stats of every feed
+    Json,
+}
+
+/// What the fetched data holds for a single configured feed
+#[derive(Debug, PartialEq, Serialize)]
+struct FeedStats {
+    slug: String,
+    item_count: usize,
+    newest: Option<DateTime<Utc>>,
+    oldest: Option<DateTime<Utc>>,
+    last_7_days: usize,
+    last_30_days: usize,
+    /// In characters, 0 if the feed has no items
+    average_description_length: usize,
+}
+
+pub fn run(config: Config, args: StatsArgs) -> Result<()> {
+    let items = load_items(Path::new(&config.output_config.item_data_output_path))?;
+    let stats = feed_stats(&config, &items, Utc::now());
+    match args.output {
+        OutputFormat::Text => {
+            for feed in &stats {
+                println!("{}", describe(feed));
+            }
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+    }
+    Ok(())
+}
+
+fn load_items(path: &Path) -> Result<Vec<ItemOutput>> {
+    let content = std::fs::read_to_string(path).with_context(|| {
+        format!(
+            "Failed to read item data, run fetch first: {}",
+            path.display()
+        )
+    })?;
+    serde_json::from_str(&content)
+        .with_context(|| format!("Invalid item data in file: {}", path.display()))
+}
+
+/// Stats for every configured feed, including those without any items, with
+/// the most active feeds first
+fn feed_stats(config: &Config, items: &[ItemOutput], now: DateTime<Utc>) -> Vec<FeedStats> {
+    let mut stats: Vec<_> = config
+        .feeds
+        .keys()
+        .map(|slug| {
+            let items: Vec<_> = items.iter().filter(|io| &io.slug == slug).collect();
+            let dates: Vec<_> = items.iter().filter_map(|io| io.item.pub_date).collect();
+            let since = |days| {
+                let cutoff = now - TimeDelta::days(days);
+                dates.iter().filter(|&&date| date >= cutoff).count()
+            };
+            let description_length: usize = items
+                .iter()
+                .map(|io| io.item.description.chars().count())
+                .sum();
+            FeedStats {
+                slug: slug.clone(),
+                item_count: items.len(),
+                newest: dates.iter().max().copied(),
+                oldest: dates.iter().min().copied(),
+                last_7_days: since(7),
+                last_30_days: since(30),
+                average_description_length: description_length
+                    .checked_div(items.len())
+                    .unwrap_or_default(),
+            }
+        })
+        .collect();
+    stats.sort_by(|a, b| {
+        let activity = |s: &FeedStats| Reverse((s.last_7_days, s.last_30_days, s.newest));
+        activity(a)
+            .cmp(&activity(b))
+            .then_with(|| a.slug.cmp(&b.slug))
+    });
+    stats
+}
+
+fn describe(stats: &FeedStats) -> String {
+    let date = |date: Option<DateTime<Utc>>| {
+        date.map_or("none".to_string(), |date| {
+            date.format("%Y-%m-%d").to_string()
+        })
+    };
+    format!(
+        "{}: {} items, {} in the last 7 days, {} in the last 30 days, newest {}, oldest {}, average description {} characters",
+        stats.slug,
+        stats.item_count,
+        stats.last_7_days,
+        stats.last_30_days,
+        date(stats.newest),
+        date(stats.oldest),
+        stats.average_description_length
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::fetch_feeds::RssItem;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2026-10-16T12:00:00Z")
+            .unwrap()
+            .to_utc()
+    }
+
+    fn item(slug: &str, days_ago: Option<i64>, description: &str) -> ItemOutput {
+        ItemOutput {
+            meta: Config::default().feeds["example"].clone(),
+            slug: slug.to_string(),
+            item: RssItem {
+                title: "Title".to_string(),
+                item_url: "https://example.com/post".to_string(),
+                description: description.to_string(),
+                safe_description: description.to_string(),
+                pub_date: days_ago.map(|days| now() - TimeDelta::days(days)),
+            },
+        }
+    }
+
+    #[test]
+    fn test_feed_stats() {
+        let mut config = Config::default();
+        for slug in ["busy", "quiet", "dead"] {
+            let feed_info = config.feeds["example"].clone();
+            config.feeds.insert(slug.to_string(), feed_info);
+        }
+        config.feeds.remove("example");
+        let items = vec![
+            item("quiet", Some(20), "1234"),
+            item("busy", Some(1), "12"),
+            item("busy", Some(3), "1234"),
+            item("busy", None, "123456"),
+            item("busy", Some(40), "12"),
+            item("removed", Some(1), "Not configured anymore"),
+        ];
+        let now = now();
+
+        let stats = feed_stats(&config, &items, now);
+        let slugs: Vec<_> = stats.iter().map(|s| s.slug.as_str()).collect();
+        assert_eq!(slugs, ["busy", "quiet", "dead"]);
+        assert_eq!(
+            stats[0],
+            FeedStats {
+                slug: "busy".to_string(),
+                item_count: 4,
+                newest: Some(now - TimeDelta::days(1)),
+                oldest: Some(now - TimeDelta::days(40)),
+                last_7_days: 2,
+                last_30_days: 2,
+                average_description_length: 3,
+            }
+        );
+        assert_eq!((stats[1].last_7_days, stats[1].last_30_days), (0, 1));
+        assert_eq!(
+            describe(&stats[2]),
+            "dead: 0 items, 0 in the last 7 days, 0 in the last 30 days, newest none, oldest none, average description 0 characters"
+        );
+    }
+
+    #[test]
+    fn test_load_items() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("itemData.json");
+        assert!(load_items(&path).is_err());
+
+        let items = vec![item("example", Some(1), "Description")];
+        std::fs::write(&path, serde_json::to_string(&items).unwrap()).unwrap();
+        let loaded = load_items(&path).unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].slug, "example");
+        assert_eq!(loaded[0].item.pub_date, items[0].item.pub_date);
+    }
+}
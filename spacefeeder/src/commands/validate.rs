@@ -0,0 +1,169 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use clap::Args;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::commands::fetch_feeds::{detailed_fetcher, entry_date, parse_feed, FetchedFeed};
+use crate::config::Config;
+use crate::error::FeedError;
+
+#[derive(Args, Debug)]
+pub struct ValidateArgs {
+    /// How many feeds may fail before validation as a whole fails
+    #[arg(long, default_value_t = 0)]
+    pub allow_failures: usize,
+}
+
+/// What a feed that could be fetched and parsed contains
+#[derive(Debug)]
+struct FeedStatus {
+    http_status: u16,
+    /// Where the feed redirected to, if it moved
+    redirected_to: Option<String>,
+    entry_count: usize,
+    newest: Option<DateTime<Utc>>,
+}
+
+pub fn run(config: Config, args: ValidateArgs) -> Result<()> {
+    let fetcher = detailed_fetcher(&config.parse_config);
+    run_with_fetcher(config, args, fetcher)
+}
+
+fn run_with_fetcher<F>(config: Config, args: ValidateArgs, fetcher: F) -> Result<()>
+where
+    F: Fn(&str) -> Result<FetchedFeed, FeedError> + Sync,
+{
+    let mut results: Vec<_> = config
+        .feeds
+        .par_iter()
+        .map(|(slug, feed_info)| {
+            let result = feed_info
+                .is_enabled()
+                .then(|| check_feed(&fetcher, &feed_info.url));
+            (slug, result)
+        })
+        .collect();
+    results.sort_unstable_by_key(|(slug, _)| *slug);
+
+    let now = Utc::now();
+    let (mut checked, mut failures) = (0, 0);
+    for (slug, result) in &results {
+        let Some(result) = result else {
+            println!("{slug}: disabled");
+            continue;
+        };
+        checked += 1;
+        match result {
+            Ok(status) => println!("{slug}: ok, {}", describe(status, now)),
+            Err(e) => {
+                failures += 1;
+                println!("{slug}: failed, {e}");
+            }
+        }
+    }
+    if failures > args.allow_failures {
+        bail!(
+            "{failures} of {checked} feeds failed validation (allowed: {})",
+            args.allow_failures
+        );
+    }
+    log::info!("{} of {checked} feeds are valid", checked - failures);
+    Ok(())
+}
+
+fn check_feed<F>(fetcher: &F, url: &str) -> Result<FeedStatus, FeedError>
+where
+    F: Fn(&str) -> Result<FetchedFeed, FeedError>,
+{
+    let fetched = fetcher(url)?;
+    let feed = parse_feed(&fetched.body)?;
+    Ok(FeedStatus {
+        http_status: fetched.status,
+        redirected_to: (fetched.url != url).then_some(fetched.url),
+        entry_count: feed.entries.len(),
+        newest: feed.entries.iter().filter_map(entry_date).max(),
+    })
+}
+
+fn describe(status: &FeedStatus, now: DateTime<Utc>) -> String {
+    let newest = match status.newest {
+        Some(newest) => format!("newest {} days old", (now - newest).num_days()),
+        None => "no dated entries".to_string(),
+    };
+    let mut description = format!(
+        "HTTP {}, {} entries, {newest}",
+        status.http_status, status.entry_count
+    );
+    if let Some(redirected_to) = &status.redirected_to {
+        description.push_str(&format!(", redirected to {redirected_to}"));
+    }
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    const FEED: &str = include_str!("../test_data/xeiaso.rss");
+
+    fn fetched(url: &str, body: &str) -> Result<FetchedFeed, FeedError> {
+        Ok(FetchedFeed {
+            status: 200,
+            url: url.to_string(),
+            body: body.as_bytes().to_vec(),
+        })
+    }
+
+    #[test_case(0, false; "failure not allowed")]
+    #[test_case(1, true; "failure allowed")]
+    fn test_validate(allow_failures: usize, expected_ok: bool) {
+        let mut config = Config::default();
+        let mut broken = config.feeds["example"].clone();
+        broken.url = "https://broken.example.com/feed".to_string();
+        config.feeds.insert("broken".to_string(), broken);
+        let args = ValidateArgs { allow_failures };
+        let fetcher = |url: &str| match url {
+            "https://broken.example.com/feed" => Err(FeedError::Status(404)),
+            _ => fetched(url, FEED),
+        };
+
+        let result = run_with_fetcher(config, args, fetcher);
+        assert_eq!(result.is_ok(), expected_ok);
+    }
+
+    #[test]
+    fn test_check_feed() {
+        let fetcher = |url: &str| fetched(url, FEED);
+        let status = check_feed(&fetcher, "https://example.com/feed").unwrap();
+        assert!(status.entry_count > 0);
+        let newest = status.newest.unwrap();
+        let description = describe(&status, newest + chrono::Duration::days(3));
+        assert_eq!(
+            description,
+            format!(
+                "HTTP 200, {} entries, newest 3 days old",
+                status.entry_count
+            )
+        );
+
+        let fetcher = |url: &str| fetched(url, "<html>Not a feed</html>");
+        let result = check_feed(&fetcher, "https://example.com/feed");
+        assert!(matches!(result, Err(FeedError::Parse(_))), "{result:?}");
+    }
+
+    #[test]
+    fn test_check_redirected_feed() {
+        let fetcher = |_: &str| fetched("https://example.com/new-feed", FEED);
+        let status = check_feed(&fetcher, "https://example.com/feed").unwrap();
+        assert_eq!(
+            status.redirected_to.as_deref(),
+            Some("https://example.com/new-feed")
+        );
+        let description = describe(&status, status.newest.unwrap());
+        assert!(
+            description.ends_with(", redirected to https://example.com/new-feed"),
+            "{description}"
+        );
+    }
+}
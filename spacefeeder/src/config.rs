@@ -1,31 +1,81 @@
 use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Result};
-use serde::Deserialize;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use toml_edit::{DocumentMut, Item, TableLike};
+use url::Url;
 
 use crate::{FeedInfo, Tier};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Config {
     #[serde(flatten)]
     pub(crate) parse_config: ParseConfig,
     #[serde(flatten)]
     pub(crate) output_config: OutputConfig,
+    /// Tiers in order from least to most loved. The site templates only
+    /// feature and colour the default "love" and "like" tiers, so items in
+    /// custom tiers are written to the data files but not highlighted
+    #[serde(default = "default_tiers")]
+    pub(crate) tiers: Vec<Tier>,
     pub(crate) feeds: HashMap<String, FeedInfo>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ParseConfig {
     pub(crate) max_articles: usize,
+    /// Keep only this many of the newest items across all feeds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) max_total_items: Option<usize>,
     pub(crate) description_max_words: usize,
+    /// Appended to descriptions cut off mid-sentence, empty for none
+    #[serde(default = "default_truncation_suffix")]
+    pub(crate) truncation_suffix: String,
+    /// Drop items published more than this many days ago
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) max_item_age_days: Option<u64>,
+    /// How often to retry a feed after a network error or server error
+    #[serde(default = "default_fetch_retries")]
+    pub(crate) fetch_retries: u32,
+    /// User-Agent header sent when fetching feeds, defaults to ureq's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) user_agent: Option<String>,
+    /// Whether items without a date survive the max_item_age_days cutoff
+    #[serde(default = "default_keep_undated_items")]
+    pub(crate) keep_undated_items: bool,
+    /// Where to look for an item's description. The first source that has
+    /// one wins, even if a later source has a longer one. Only among several
+    /// media descriptions is the longest picked
+    #[serde(default = "default_description_source_order")]
+    pub(crate) description_source_order: Vec<DescriptionSource>,
 }
 
-#[derive(Debug, Deserialize)]
+/// A field of a feed entry that can provide its description
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DescriptionSource {
+    Summary,
+    Content,
+    Media,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 pub struct OutputConfig {
     #[serde(default = "default_feed_data_output_path")]
     pub(crate) feed_data_output_path: String,
     #[serde(default = "default_item_data_output_path")]
     pub(crate) item_data_output_path: String,
+    /// Directory in which a `<tier>Data.json` file is written for each tier
+    #[serde(default = "default_tier_data_output_dir")]
+    pub(crate) tier_data_output_dir: String,
+    /// Summary of the last fetch, placed in static/ so it ends up in the site
+    #[serde(default = "default_build_info_output_path")]
+    pub(crate) build_info_output_path: String,
+    /// Where the site is built to, only used to clean it up
+    #[serde(default = "default_site_output_dir")]
+    pub(crate) site_output_dir: String,
 }
 
 fn default_feed_data_output_path() -> String {
@@ -36,14 +86,181 @@ fn default_item_data_output_path() -> String {
     "./content/data/itemData.json".to_string()
 }
 
+fn default_tier_data_output_dir() -> String {
+    "./content/data".to_string()
+}
+
+fn default_build_info_output_path() -> String {
+    "./static/build-info.json".to_string()
+}
+
+fn default_site_output_dir() -> String {
+    "./public".to_string()
+}
+
+fn default_truncation_suffix() -> String {
+    "…".to_string()
+}
+
+fn default_fetch_retries() -> u32 {
+    2
+}
+
+fn default_keep_undated_items() -> bool {
+    true
+}
+
+fn default_description_source_order() -> Vec<DescriptionSource> {
+    vec![
+        DescriptionSource::Summary,
+        DescriptionSource::Content,
+        DescriptionSource::Media,
+    ]
+}
+
+fn default_tiers() -> Vec<Tier> {
+    ["new", "like", "love"]
+        .into_iter()
+        .map(|name| Tier(name.to_string()))
+        .collect()
+}
+
+const CONFIG_FILE_NAME: &str = "spacefeeder.toml";
+
+/// Options without a default and an example value for each, so that
+/// `to_toml` can show them even when they are unset
+const UNSET_OPTIONS: &[(&str, &str)] = &[
+    ("max_total_items", "500"),
+    ("max_item_age_days", "30"),
+    ("user_agent", "\"Mozilla/5.0 (compatible; feed.me)\""),
+];
+const UNSET_FEED_OPTIONS: &[(&str, &str)] = &[("enabled", "false")];
+
+/// Pick the config file to use: `explicit` if given, else `./spacefeeder.toml`,
+/// else the global one in `$XDG_CONFIG_HOME/feed.me/`
+pub fn resolve_path(explicit: Option<&Path>) -> Result<PathBuf> {
+    let path = resolve_path_from(explicit, Path::new("."), |key| std::env::var_os(key))?;
+    log::info!("Using config {}", path.display());
+    Ok(path)
+}
+
+fn resolve_path_from(
+    explicit: Option<&Path>,
+    cwd: &Path,
+    env: impl Fn(&str) -> Option<OsString>,
+) -> Result<PathBuf> {
+    if let Some(path) = explicit {
+        return Ok(path.to_path_buf());
+    }
+    let local = cwd.join(CONFIG_FILE_NAME);
+    if local.is_file() {
+        return Ok(local);
+    }
+    let config_home = env("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env("HOME").map(|home| PathBuf::from(home).join(".config")));
+    let global = config_home.map(|dir| dir.join("feed.me").join(CONFIG_FILE_NAME));
+    match global {
+        Some(global) if global.is_file() => Ok(global),
+        Some(global) => bail!(
+            "No config found at {} or {}, pass --config-path to use another file",
+            local.display(),
+            global.display()
+        ),
+        None => bail!(
+            "No config found at {}, pass --config-path to use another file",
+            local.display()
+        ),
+    }
+}
+
 impl Config {
-    pub fn from_file(path: &str) -> Result<Self> {
+    pub fn from_file(path: &Path) -> Result<Self> {
         let content = std::fs::read_to_string(path)
-            .with_context(|| format!("Failed to read file: {path}"))?;
-        let config = toml_edit::de::from_str(&content)
-            .with_context(|| format!("Failed to parse TOML from file: {path}"))?;
+            .with_context(|| format!("Failed to read file: {}", path.display()))?;
+        Self::from_toml(&content)
+            .with_context(|| format!("Invalid config in file: {}", path.display()))
+    }
+
+    fn from_toml(content: &str) -> Result<Self> {
+        let mut config: Self = toml_edit::de::from_str(content).context("Failed to parse TOML")?;
+        config.normalize_feed_urls()?;
+        config.validate()?;
         Ok(config)
     }
+
+    /// Serialize the config to TOML, including every field with its value.
+    /// Options that are unset are included as comments with an example value
+    pub fn to_toml(&self) -> Result<String> {
+        let toml =
+            toml_edit::ser::to_string_pretty(self).context("Failed to serialize config to TOML")?;
+        let mut doc: DocumentMut = toml.parse().context("Failed to parse serialized config")?;
+
+        let unset = |table: &dyn TableLike, options: &[(&str, &str)]| -> String {
+            options
+                .iter()
+                .filter(|(key, _)| !table.contains_key(key))
+                .map(|(key, example)| format!("# {key} = {example}\n"))
+                .collect()
+        };
+        let root_comments = unset(doc.as_table(), UNSET_OPTIONS);
+        let feeds = doc.get_mut("feeds").and_then(Item::as_table_like_mut);
+        for (_, feed) in feeds.into_iter().flat_map(|feeds| feeds.iter_mut()) {
+            let Some(feed) = feed.as_table_like_mut() else {
+                continue;
+            };
+            let comments = unset(feed, UNSET_FEED_OPTIONS);
+            if let Some(mut first_key) = feed.iter_mut().next().map(|(key, _)| key) {
+                first_key.leaf_decor_mut().set_prefix(comments);
+            }
+        }
+        Ok(format!("{root_comments}{doc}"))
+    }
+
+    /// Give protocol-relative feed URLs a scheme and reject anything that
+    /// isn't then a full http(s) URL, such as root-relative or schemeless ones
+    fn normalize_feed_urls(&mut self) -> Result<()> {
+        for (slug, feed_info) in &mut self.feeds {
+            if feed_info.url.starts_with("//") {
+                feed_info.url = format!("https:{}", feed_info.url);
+            } else if feed_info.url.starts_with('/') {
+                bail!(
+                    "Feed {slug} has relative URL {}, expected a full URL like https://example.com{}",
+                    feed_info.url,
+                    feed_info.url
+                );
+            }
+            let url = Url::parse(&feed_info.url).with_context(|| {
+                format!(
+                    "Feed {slug} has invalid URL {}, expected a full URL like https://{}",
+                    feed_info.url, feed_info.url
+                )
+            })?;
+            if !matches!(url.scheme(), "http" | "https") {
+                bail!(
+                    "Feed {slug} has URL {} with unsupported scheme {}, expected http or https",
+                    feed_info.url,
+                    url.scheme()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<()> {
+        for (slug, feed_info) in &self.feeds {
+            if !self.tiers.contains(&feed_info.tier) {
+                let tiers: Vec<_> = self.tiers.iter().map(Tier::as_str).collect();
+                bail!(
+                    "Feed {slug} has unknown tier {}, expected one of: {}",
+                    feed_info.tier,
+                    tiers.join(", ")
+                );
+            }
+        }
+        Ok(())
+    }
 }
 
 impl Default for Config {
@@ -51,20 +268,187 @@ impl Default for Config {
         Self {
             parse_config: ParseConfig {
                 max_articles: 5,
+                max_total_items: None,
                 description_max_words: 150,
+                truncation_suffix: default_truncation_suffix(),
+                max_item_age_days: None,
+                fetch_retries: default_fetch_retries(),
+                user_agent: None,
+                keep_undated_items: default_keep_undated_items(),
+                description_source_order: default_description_source_order(),
             },
             output_config: OutputConfig {
                 feed_data_output_path: default_feed_data_output_path(),
                 item_data_output_path: default_item_data_output_path(),
+                tier_data_output_dir: default_tier_data_output_dir(),
+                build_info_output_path: default_build_info_output_path(),
+                site_output_dir: default_site_output_dir(),
             },
+            tiers: default_tiers(),
             feeds: HashMap::from([(
                 "example".to_string(),
                 FeedInfo {
-                    url: "www.example.com".to_string(),
+                    url: "https://www.example.com/feed.xml".to_string(),
                     author: "Example Author".to_string(),
-                    tier: Tier::New,
+                    tier: Tier("new".to_string()),
+                    enabled: None,
                 },
             )]),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    const BASE_CONFIG: &str = r#"
+max_articles = 5
+description_max_words = 150
+"#;
+
+    #[test]
+    fn test_legacy_tiers_are_default() {
+        let config = Config::from_toml(&format!(
+            r#"{BASE_CONFIG}
+[feeds.example]
+url = "https://example.com/feed"
+author = "Example"
+tier = "love"
+"#
+        ))
+        .unwrap();
+        let tiers: Vec<_> = config.tiers.iter().map(Tier::as_str).collect();
+        assert_eq!(tiers, ["new", "like", "love"]);
+        assert_eq!(config.feeds["example"].tier.as_str(), "love");
+    }
+
+    #[test]
+    fn test_custom_tiers() {
+        let config = Config::from_toml(&format!(
+            r#"{BASE_CONFIG}
+tiers = ["archive", "weekly", "daily"]
+
+[feeds.example]
+url = "https://example.com/feed"
+author = "Example"
+tier = "weekly"
+"#
+        ))
+        .unwrap();
+        let tiers: Vec<_> = config.tiers.iter().map(Tier::as_str).collect();
+        assert_eq!(tiers, ["archive", "weekly", "daily"]);
+
+        let feed_info = &config.feeds["example"];
+        let serialized = serde_json::to_string(feed_info).unwrap();
+        assert!(serialized.contains(r#""tier":"weekly""#));
+        let round_tripped: FeedInfo = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(round_tripped.tier, feed_info.tier);
+    }
+
+    #[test]
+    fn test_default_config_round_trips() {
+        let printed = Config::default().to_toml().unwrap();
+        let config = Config::from_toml(&printed).unwrap();
+        assert_eq!(config.tiers, default_tiers());
+        assert_eq!(
+            config.output_config.tier_data_output_dir,
+            default_tier_data_output_dir()
+        );
+        assert!(config.feeds.contains_key("example"));
+
+        // Every option shows up, even those left unset by default
+        let mut config = Config::default();
+        config.parse_config.max_total_items = Some(500);
+        config.parse_config.max_item_age_days = Some(30);
+        config.parse_config.user_agent = Some("feed.me".to_string());
+        for feed_info in config.feeds.values_mut() {
+            feed_info.enabled = Some(false);
+        }
+        let everything: DocumentMut = config.to_toml().unwrap().parse().unwrap();
+        let feed = everything["feeds"]["example"].as_table_like().unwrap();
+        let keys = everything
+            .as_table()
+            .iter()
+            .chain(feed.iter())
+            .map(|(key, _)| key)
+            .filter(|key| *key != "feeds");
+        for key in keys {
+            assert!(printed.contains(&format!("{key} =")), "{key} missing");
+        }
+    }
+
+    #[test_case(true, true, true, Some("site/explicit.toml"); "explicit path wins")]
+    #[test_case(false, true, true, Some("site/spacefeeder.toml"); "local before global")]
+    #[test_case(false, false, true, Some(".config/feed.me/spacefeeder.toml"); "global fallback")]
+    #[test_case(false, false, false, None; "no config")]
+    fn test_resolve_path(explicit: bool, local: bool, global: bool, expected: Option<&str>) {
+        let dir = tempfile::tempdir().unwrap();
+        let cwd = dir.path().join("site");
+        std::fs::create_dir_all(&cwd).unwrap();
+        let global_dir = dir.path().join(".config/feed.me");
+        std::fs::create_dir_all(&global_dir).unwrap();
+        if local {
+            std::fs::write(cwd.join(CONFIG_FILE_NAME), "").unwrap();
+        }
+        if global {
+            std::fs::write(global_dir.join(CONFIG_FILE_NAME), "").unwrap();
+        }
+        let explicit = explicit.then(|| cwd.join("explicit.toml"));
+        let env = |key: &str| (key == "HOME").then(|| dir.path().as_os_str().to_owned());
+
+        let resolved = resolve_path_from(explicit.as_deref(), &cwd, env).ok();
+        assert_eq!(resolved, expected.map(|path| dir.path().join(path)));
+    }
+
+    #[test]
+    fn test_xdg_config_home() {
+        let dir = tempfile::tempdir().unwrap();
+        let global_dir = dir.path().join("xdg/feed.me");
+        std::fs::create_dir_all(&global_dir).unwrap();
+        std::fs::write(global_dir.join(CONFIG_FILE_NAME), "").unwrap();
+        let env = |key: &str| match key {
+            "XDG_CONFIG_HOME" => Some(dir.path().join("xdg").into_os_string()),
+            "HOME" => Some(dir.path().join("home").into_os_string()),
+            _ => None,
+        };
+
+        let resolved = resolve_path_from(None, dir.path(), env).unwrap();
+        assert_eq!(resolved, global_dir.join(CONFIG_FILE_NAME));
+    }
+
+    #[test_case("//example.com/feed", Some("https://example.com/feed"); "protocol relative")]
+    #[test_case("/feed.xml", None; "root relative")]
+    #[test_case("http://example.com/feed", Some("http://example.com/feed"); "absolute")]
+    #[test_case("www.example.com/feed", None; "schemeless")]
+    #[test_case("example.com:8080/feed", None; "host mistaken for scheme")]
+    #[test_case("ftp://example.com/feed", None; "unsupported scheme")]
+    fn test_feed_url_normalization(url: &str, expected: Option<&str>) {
+        let result = Config::from_toml(&format!(
+            r#"{BASE_CONFIG}
+[feeds.example]
+url = "{url}"
+author = "Example"
+tier = "new"
+"#
+        ));
+        let url = result
+            .ok()
+            .map(|config| config.feeds["example"].url.clone());
+        assert_eq!(url.as_deref(), expected);
+    }
+
+    #[test]
+    fn test_unknown_tier_is_rejected() {
+        let result = Config::from_toml(&format!(
+            r#"{BASE_CONFIG}
+[feeds.example]
+url = "https://example.com/feed"
+author = "Example"
+tier = "weekly"
+"#
+        ));
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,87 @@
+use std::io;
+
+use thiserror::Error;
+
+/// Why fetching a single feed failed
+#[derive(Debug, Error)]
+pub enum FeedError {
+    #[error("request failed: {0}")]
+    Network(#[source] Box<ureq::Error>),
+    #[error("server responded with HTTP {0}")]
+    Status(u16),
+    #[error("blocked by bot protection (HTTP {0}), try setting a browser-like user_agent")]
+    Blocked(u16),
+    #[error("timed out")]
+    Timeout,
+    #[error("failed to read response body: {0}")]
+    Decode(#[source] io::Error),
+    #[error("response body is empty")]
+    Empty,
+    #[error("failed to parse feed: {0}")]
+    Parse(#[from] feed_rs::parser::ParseFeedError),
+}
+
+/// Signs of a Cloudflare or similar JavaScript challenge page
+const CHALLENGE_MARKERS: &[&str] = &[
+    "cf-browser-verification",
+    "challenge-platform",
+    "<title>Just a moment...</title>",
+    "Attention Required! | Cloudflare",
+];
+
+impl From<ureq::Error> for FeedError {
+    fn from(error: ureq::Error) -> Self {
+        if let ureq::Error::Status(status, response) = error {
+            return Self::from_status(status, response);
+        }
+        let timed_out = std::error::Error::source(&error)
+            .and_then(|source| source.downcast_ref::<io::Error>())
+            .is_some_and(is_timeout);
+        if timed_out {
+            Self::Timeout
+        } else {
+            Self::Network(Box::new(error))
+        }
+    }
+}
+
+impl FeedError {
+    /// Whether trying again later might succeed
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Network(_) | Self::Timeout => true,
+            Self::Status(status) => *status == 429 || *status >= 500,
+            Self::Blocked(_) | Self::Decode(_) | Self::Empty | Self::Parse(_) => false,
+        }
+    }
+
+    fn from_status(status: u16, response: ureq::Response) -> Self {
+        if !matches!(status, 403 | 503) {
+            return Self::Status(status);
+        }
+        let mitigated = response.header("cf-mitigated").is_some();
+        let body = response.into_string().unwrap_or_default();
+        if mitigated || CHALLENGE_MARKERS.iter().any(|marker| body.contains(marker)) {
+            Self::Blocked(status)
+        } else {
+            Self::Status(status)
+        }
+    }
+}
+
+impl From<io::Error> for FeedError {
+    fn from(error: io::Error) -> Self {
+        if is_timeout(&error) {
+            Self::Timeout
+        } else {
+            Self::Decode(error)
+        }
+    }
+}
+
+fn is_timeout(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+    )
+}
@@ -1,18 +1,141 @@
 pub mod commands;
 pub mod config;
+pub mod error;
+pub mod logging;
+pub mod text_utils;
 
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Error, Result};
 use serde::{Deserialize, Serialize};
 #[derive(Clone, Debug, Deserialize, Serialize)]
 struct FeedInfo {
     url: String,
     author: String,
     tier: Tier,
+    /// Disabled feeds stay in the config but aren't fetched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    enabled: Option<bool>,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
-#[serde(rename_all = "lowercase")]
-enum Tier {
-    New,
-    Like,
-    Love,
+impl FeedInfo {
+    fn is_enabled(&self) -> bool {
+        self.enabled != Some(false)
+    }
+}
+
+/// Whether a tier name or feed slug only has characters that are safe to use
+/// in a file name: lowercase ASCII letters, digits, '_' and '-'
+fn is_file_name_safe(name: &str) -> bool {
+    let is_valid = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-';
+    !name.is_empty() && name.chars().all(is_valid)
+}
+
+/// The name of a tier, e.g. "new", "like" or "love".
+///
+/// The set of valid tiers and their order is defined by the config.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+struct Tier(String);
+
+impl Tier {
+    fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for Tier {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_lowercase();
+        if name.is_empty() {
+            return Err(anyhow!("Tier name must not be empty"));
+        }
+        // Tier names end up in file names, see `tier_data_output_dir`
+        if !is_file_name_safe(&name) {
+            return Err(anyhow!(
+                "Invalid tier name {name:?}, only letters, digits, '_' and '-' are allowed"
+            ));
+        }
+        Ok(Self(name))
+    }
+}
+
+impl TryFrom<String> for Tier {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Tier> for String {
+    fn from(tier: Tier) -> Self {
+        tier.0
+    }
+}
+
+impl fmt::Display for Tier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Parse a duration such as "90s", "30m", "12h" or "7d". A bare number is
+/// taken to be seconds.
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let split_at = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (amount, unit) = s.split_at(split_at);
+    let amount: u64 = amount
+        .parse()
+        .with_context(|| format!("Invalid duration: {s}"))?;
+    let unit_seconds: u64 = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        _ => bail!("Invalid duration unit in {s}, expected one of s, m, h, d"),
+    };
+    let seconds = amount
+        .checked_mul(unit_seconds)
+        .with_context(|| format!("Invalid duration: {s}"))?;
+    Ok(Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("45", 45; "bare seconds")]
+    #[test_case("90s", 90; "seconds")]
+    #[test_case("30m", 30 * 60; "minutes")]
+    #[test_case("12h", 12 * 60 * 60; "hours")]
+    #[test_case("7d", 7 * 24 * 60 * 60; "days")]
+    fn test_parse_duration(input: &str, seconds: u64) {
+        assert_eq!(parse_duration(input).unwrap(), Duration::from_secs(seconds));
+    }
+
+    #[test_case(""; "empty")]
+    #[test_case("m"; "no amount")]
+    #[test_case("5w"; "unknown unit")]
+    #[test_case("999999999999999999d"; "overflow")]
+    fn test_parse_duration_invalid(input: &str) {
+        assert!(parse_duration(input).is_err());
+    }
+
+    #[test_case(" Love ", Some("love"); "normalized")]
+    #[test_case("must-read_2", Some("must-read_2"); "dash underscore digit")]
+    #[test_case("", None; "empty")]
+    #[test_case("../../x", None; "path traversal")]
+    #[test_case("a/b", None; "separator")]
+    #[test_case("café", None; "non ascii")]
+    fn test_tier_name(input: &str, expected: Option<&str>) {
+        let tier = input.parse::<Tier>().ok();
+        assert_eq!(tier.as_ref().map(Tier::as_str), expected);
+    }
 }
@@ -0,0 +1,77 @@
+use log::LevelFilter;
+
+/// Map the CLI verbosity flags to the most verbose level that gets logged.
+///
+/// Progress is logged at info level, per-feed detail at debug level.
+pub fn level_filter(quiet: bool, verbose: u8) -> LevelFilter {
+    if quiet {
+        return LevelFilter::Warn;
+    }
+    match verbose {
+        0 => LevelFilter::Info,
+        1 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+pub fn init(quiet: bool, verbose: u8) {
+    env_logger::Builder::new()
+        .filter_level(level_filter(quiet, verbose))
+        .format_timestamp(None)
+        .format_target(false)
+        .parse_default_env()
+        .init();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use log::{Log, Metadata, Record};
+
+    use super::*;
+
+    struct CapturingLogger {
+        lines: Mutex<Vec<String>>,
+    }
+
+    impl Log for CapturingLogger {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            metadata.level() <= log::max_level()
+        }
+
+        fn log(&self, record: &Record) {
+            if self.enabled(record.metadata()) && record.target() == module_path!() {
+                let line = format!("{}: {}", record.level(), record.args());
+                self.lines.lock().unwrap().push(line);
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CapturingLogger = CapturingLogger {
+        lines: Mutex::new(Vec::new()),
+    };
+
+    #[test]
+    fn test_quiet_mode_suppresses_info() {
+        log::set_logger(&LOGGER).unwrap();
+
+        log::set_max_level(level_filter(true, 0));
+        log::info!("Fetched feed for example");
+        log::warn!("Failed to load feed for example");
+        assert_eq!(
+            *LOGGER.lines.lock().unwrap(),
+            ["WARN: Failed to load feed for example"]
+        );
+
+        LOGGER.lines.lock().unwrap().clear();
+        log::set_max_level(level_filter(false, 1));
+        log::debug!("Building feed for example");
+        assert_eq!(
+            *LOGGER.lines.lock().unwrap(),
+            ["DEBUG: Building feed for example"]
+        );
+    }
+}
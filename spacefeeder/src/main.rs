@@ -1,41 +1,103 @@
+use std::path::PathBuf;
+
 use anyhow::Result;
-use clap::{arg, command, Parser, Subcommand};
+use clap::{Parser, Subcommand};
 use spacefeeder::{
-    commands::{fetch_feeds, find_feed},
-    config,
+    commands::{clean, edit_feed, fetch_feeds, find_feed, stats, validate},
+    config, logging,
 };
 
 #[derive(Parser)]
 #[command(name = "Space Feeder", about = "Processes RSS and Atom feeds")]
 struct Cli {
+    /// Only print warnings and errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Print more detail, e.g. per feed progress (repeat for more)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+    /// Path to the config file [default: ./spacefeeder.toml, then
+    /// ~/.config/feed.me/spacefeeder.toml]
+    #[arg(long, global = true)]
+    config_path: Option<PathBuf>,
     #[command(subcommand)]
     command: Commands,
 }
 #[derive(Subcommand)]
 enum Commands {
-    Fetch {
-        /// Path to the config file
-        #[arg(long, default_value = "./spacefeeder.toml")]
-        config_path: String,
-    },
+    Fetch(fetch_feeds::FetchArgs),
     FindFeed {
         #[arg(long)]
         base_url: String,
+        /// How to print the result, progress always goes to stderr
+        #[arg(long, value_enum, default_value_t)]
+        format: find_feed::OutputFormat,
     },
+    /// Print a config with every available option set to its default
+    PrintDefaultConfig,
+    /// Remove fetched feed data and the built site
+    Clean(clean::CleanArgs),
+    /// Check that every configured feed can be fetched and parsed
+    Validate(validate::ValidateArgs),
+    /// Show how many items each configured feed contributes to the fetched data
+    Stats(stats::StatsArgs),
+    /// Stop fetching a feed without removing it from the config
+    Disable(edit_feed::FeedArgs),
+    /// Fetch a disabled feed again
+    Enable(edit_feed::FeedArgs),
+    /// Give a feed a new slug, keeping its fetched items
+    Rename(edit_feed::RenameArgs),
+    /// Move a feed to the next more loved tier
+    Promote(edit_feed::FeedArgs),
+    /// Move a feed to the next less loved tier
+    Demote(edit_feed::FeedArgs),
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    logging::init(cli.quiet, cli.verbose);
 
+    // These don't read the config, so they work without one
+    match &cli.command {
+        Commands::FindFeed { base_url, format } => {
+            let discovery = find_feed::run(base_url)?;
+            match format {
+                find_feed::OutputFormat::Text => println!("{}", discovery.into_url()?),
+                find_feed::OutputFormat::Json => {
+                    println!("{}", serde_json::to_string_pretty(&discovery)?)
+                }
+            }
+            return Ok(());
+        }
+        Commands::PrintDefaultConfig => {
+            print!("{}", config::Config::default().to_toml()?);
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let config_path = config::resolve_path(cli.config_path.as_deref())?;
     match cli.command {
-        Commands::Fetch { config_path } => {
+        Commands::Fetch(args) => {
             let config = config::Config::from_file(&config_path)?;
-            fetch_feeds::run(config)
+            if args.watch {
+                fetch_feeds::watch(config, &config_path, args)
+            } else {
+                fetch_feeds::run(config, args)
+            }
+        }
+        Commands::Clean(args) => clean::run(config::Config::from_file(&config_path)?, args),
+        Commands::Validate(args) => validate::run(config::Config::from_file(&config_path)?, args),
+        Commands::Stats(args) => stats::run(config::Config::from_file(&config_path)?, args),
+        Commands::Disable(args) => edit_feed::set_enabled(&config_path, args, false),
+        Commands::Enable(args) => edit_feed::set_enabled(&config_path, args, true),
+        Commands::Rename(args) => edit_feed::rename(&config_path, args),
+        Commands::Promote(args) => {
+            edit_feed::move_tier(&config_path, args, edit_feed::TierStep::Promote)
         }
-        Commands::FindFeed { base_url } => {
-            let url_match = find_feed::run(&base_url)?;
-            println!("{url_match}");
-            Ok(())
+        Commands::Demote(args) => {
+            edit_feed::move_tier(&config_path, args, edit_feed::TierStep::Demote)
         }
+        Commands::FindFeed { .. } | Commands::PrintDefaultConfig => unreachable!("handled above"),
     }
 }
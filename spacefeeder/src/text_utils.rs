@@ -0,0 +1,111 @@
+/// Words ending in a period that rarely end a sentence
+const ABBREVIATIONS: &[&str] = &[
+    "e.g.", "i.e.", "cf.", "vs.", "approx.", "mr.", "mrs.", "ms.", "dr.", "prof.", "st.",
+];
+
+/// Shortens text to a word budget, preferring to end at a sentence boundary.
+pub struct Summarizer<'a> {
+    max_words: usize,
+    suffix: &'a str,
+}
+
+impl<'a> Summarizer<'a> {
+    /// Summaries are at most `max_words` words long, with `suffix` appended
+    /// when one had to be cut off mid-sentence
+    pub fn new(max_words: usize, suffix: &'a str) -> Self {
+        Self { max_words, suffix }
+    }
+
+    /// Shorten `text` to at most `max_words` words.
+    ///
+    /// Whole sentences are kept where possible, so the summary ends at a sentence
+    /// or paragraph boundary. If not even the first sentence fits, the text is
+    /// cut after `max_words` words, trailing punctuation is dropped and `suffix`
+    /// is appended. Whitespace is collapsed to single spaces.
+    pub fn summarize(&self, text: &str) -> String {
+        let max_words = self.max_words;
+        let text = text.replace("\r\n", "\n");
+        let mut words = Vec::new();
+        // Word counts after which a sentence or paragraph ends
+        let mut boundaries = Vec::new();
+        for paragraph in text.split("\n\n") {
+            for word in paragraph.split_whitespace() {
+                words.push(word);
+                if ends_sentence(word) {
+                    boundaries.push(words.len());
+                }
+            }
+            boundaries.push(words.len());
+        }
+
+        if words.len() <= max_words {
+            return words.join(" ");
+        }
+        match boundaries.iter().rev().find(|&&end| end <= max_words) {
+            Some(&end) if end > 0 => words[..end].join(" "),
+            _ => {
+                let truncated = words[..max_words].join(" ");
+                let truncated = truncated.trim_end_matches([',', ';', ':', '.', '-', '–', '—']);
+                format!("{truncated}{}", self.suffix)
+            }
+        }
+    }
+}
+
+/// Whether `word` ends a sentence, ignoring abbreviations like "e.g." and
+/// initials or acronyms like "J." and "U.S."
+fn ends_sentence(word: &str) -> bool {
+    if word.ends_with(['!', '?']) {
+        return true;
+    }
+    let Some(stem) = word.strip_suffix('.') else {
+        return false;
+    };
+    let lowercase = word.trim_start_matches(['(', '"', '“']).to_lowercase();
+    if ABBREVIATIONS.contains(&lowercase.as_str()) {
+        return false;
+    }
+    let is_initials = stem
+        .trim_start_matches(['(', '"', '“'])
+        .split('.')
+        .all(|part| part.chars().count() == 1 && part.chars().all(char::is_alphabetic));
+    !is_initials
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("A short paragraph. With two sentences.", 10, "A short paragraph. With two sentences."; "short paragraph")]
+    #[test_case("First sentence here. Second sentence here. Third sentence here.", 7, "First sentence here. Second sentence here."; "long paragraph")]
+    #[test_case("One paragraph\n\nAnother  paragraph that is long", 4, "One paragraph"; "paragraph break")]
+    #[test_case("a blob of words without any break at all", 4, "a blob of words…"; "no break blob")]
+    fn test_summarize(text: &str, max_words: usize, expected: &str) {
+        assert_eq!(Summarizer::new(max_words, "…").summarize(text), expected);
+    }
+
+    #[test_case("Prices rose in the U.S. and abroad this year.", 6, "Prices rose in the U.S. and…"; "acronym")]
+    #[test_case("Use a tool, e.g. a hammer, to fix it.", 5, "Use a tool, e.g. a…"; "abbreviation")]
+    #[test_case("Pi is roughly 3.14 and more digits follow.", 5, "Pi is roughly 3.14 and…"; "decimal")]
+    #[test_case("Written by J. Doe in May. It was long.", 7, "Written by J. Doe in May."; "initial")]
+    #[test_case("It really worked. Then it broke.", 4, "It really worked."; "real sentence end")]
+    fn test_summarize_abbreviations(text: &str, max_words: usize, expected: &str) {
+        assert_eq!(Summarizer::new(max_words, "…").summarize(text), expected);
+    }
+
+    #[test_case("one, two, three, four", "…", "one, two…"; "default suffix")]
+    #[test_case("one, two, three, four", " [more]", "one, two [more]"; "custom suffix")]
+    #[test_case("one, two, three, four", "", "one, two"; "no suffix")]
+    fn test_summarize_suffix(text: &str, suffix: &str, expected: &str) {
+        assert_eq!(Summarizer::new(2, suffix).summarize(text), expected);
+    }
+
+    #[test]
+    fn test_summarize_sentence_end_has_no_suffix() {
+        let text = "This sentence ends with a period. Then more follows";
+        let expected = "This sentence ends with a period.";
+        assert_eq!(Summarizer::new(7, "…").summarize(text), expected);
+        assert_eq!(Summarizer::new(7, "").summarize(text), expected);
+    }
+}
@@ -0,0 +1,23 @@
+#!/usr/bin/env bash
+# Build the site once per [extra.robots] variant in this directory and
+# compare the generated robots.txt with the expected <variant>.txt.
+# Needs zola and feed data, e.g. an empty content/data/itemData.json.
+set -euo pipefail
+
+cd "$(dirname "$0")/../.."
+tmp="$(mktemp -d)"
+trap 'rm -rf "$tmp"' EXIT
+
+status=0
+for variant in tests/robots/*.toml; do
+  name="$(basename "$variant" .toml)"
+  # Swap the [extra.robots] section of the site config for the variant
+  awk '/^\[extra\.robots\]/ { skip = 1; next } /^\[/ { skip = 0 } !skip' config.toml > "$tmp/$name.toml"
+  cat "$variant" >> "$tmp/$name.toml"
+  zola --config "$tmp/$name.toml" build --output-dir "$tmp/$name" > /dev/null
+  if ! diff -u "tests/robots/$name.txt" "$tmp/$name/robots.txt"; then
+    echo "robots.txt differs for $name"
+    status=1
+  fi
+done
+exit "$status"